@@ -0,0 +1,959 @@
+use anyhow::{anyhow, bail, Result};
+use openai_wrapper::{ModelConfig, Provider};
+use serde::Deserialize;
+use std::{
+    env, fmt, fs,
+    io::ErrorKind,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const DEFAULT_FILE: &str = "config.toml";
+
+/// Environment variables, the config key each one overrides, and how its
+/// value is read.
+const ENV_OVERRIDES: [(&str, &str, Kind); 51] = [
+    ("DATA_ROOT", "paths.data_root", Kind::Text),
+    ("MODELS_PATH", "paths.models", Kind::Text),
+    ("SOURCES_PATH", "paths.sources", Kind::Text),
+    ("PROMPTS_DIR", "paths.prompts", Kind::Text),
+    ("SAFETY_PATH", "paths.safety", Kind::Text),
+    ("PRICES_PATH", "paths.prices", Kind::Text),
+    ("SCHEDULE_PATH", "paths.schedule", Kind::Text),
+    ("SEEN_TWEETS_PATH", "paths.seen_tweets", Kind::Text),
+    ("RUN_HISTORY_PATH", "paths.run_history", Kind::Text),
+    ("CLIP_QUEUE_PATH", "paths.clip_queue", Kind::Text),
+    ("TWITTER_COOKIES_PATH", "paths.twitter_cookies", Kind::Text),
+    ("CONTENT_FIXTURES_DIR", "paths.content_fixtures", Kind::Text),
+    ("LLM_FIXTURES_DIR", "paths.llm_fixtures", Kind::Text),
+    ("OPENAI_API_KEY", "keys.openai", Kind::Text),
+    ("ANTHROPIC_API_KEY", "keys.anthropic", Kind::Text),
+    ("ELEVENLABS_API_KEY", "keys.elevenlabs", Kind::Text),
+    ("FAL_API_KEY", "keys.fal", Kind::Text),
+    ("YOUTUBE_ACCESS_TOKEN", "keys.youtube", Kind::Text),
+    ("TIKTOK_ACCESS_TOKEN", "keys.tiktok", Kind::Text),
+    ("INSTAGRAM_ACCESS_TOKEN", "keys.instagram", Kind::Text),
+    ("OLLAMA_URL", "providers.ollama_url", Kind::Text),
+    ("FAL_ENDPOINT", "providers.fal_endpoint", Kind::Text),
+    ("TTS_PROVIDER", "providers.tts", Kind::Text),
+    (
+        "TRANSCRIPTION_PROVIDER",
+        "providers.transcription",
+        Kind::Text,
+    ),
+    ("IMAGE_PROVIDER", "providers.images", Kind::Text),
+    ("LLM_REPLAY", "providers.llm_replay", Kind::Text),
+    ("FFMPEG_PATH", "tools.ffmpeg", Kind::Text),
+    ("FFPROBE_PATH", "tools.ffprobe", Kind::Text),
+    ("DOCKER_PATH", "tools.docker", Kind::Text),
+    ("WHISPERX_IMAGE", "tools.whisperx_image", Kind::Text),
+    ("TWITTER_USERNAME", "twitter.username", Kind::Text),
+    ("TWITTER_PASSWORD", "twitter.password", Kind::Text),
+    ("TWITTER_EMAIL", "twitter.email", Kind::Text),
+    (
+        "TWITTER_2FA_SECRET",
+        "twitter.two_factor_secret",
+        Kind::Text,
+    ),
+    ("PUBLISH_TO_TWITTER", "publish.twitter", Kind::Flag),
+    ("INSTAGRAM_USER_ID", "publish.instagram_user_id", Kind::Text),
+    (
+        "RETENTION_PRUNE_AFTER_DAYS",
+        "storage.prune_after_days",
+        Kind::Number,
+    ),
+    (
+        "RETENTION_MAX_TOTAL_MB",
+        "storage.max_total_mb",
+        Kind::Number,
+    ),
+    ("DAILY_BUDGET_USD", "budget.daily_usd", Kind::Number),
+    ("COMEDIAN_CANDIDATES", "critic.candidates", Kind::Number),
+    ("CRITIC_THRESHOLD", "critic.threshold", Kind::Number),
+    ("CRITIC_MAX_ROUNDS", "critic.max_rounds", Kind::Number),
+    (
+        "MAX_SPOKEN_SECONDS",
+        "style.max_spoken_seconds",
+        Kind::Number,
+    ),
+    ("PIPELINE_RUNS", "workers.runs", Kind::Number),
+    ("PIPELINE_TEXT_WORKERS", "workers.text", Kind::Number),
+    ("PIPELINE_TTS_WORKERS", "workers.tts", Kind::Number),
+    (
+        "PIPELINE_TRANSCRIPTION_WORKERS",
+        "workers.transcription",
+        Kind::Number,
+    ),
+    ("PIPELINE_IMAGE_WORKERS", "workers.images", Kind::Number),
+    ("PIPELINE_RENDER_WORKERS", "workers.render", Kind::Number),
+    ("METRICS_ADDR", "telemetry.metrics_addr", Kind::Text),
+    ("LOG_FORMAT", "telemetry.log_format", Kind::Text),
+];
+
+/// How an environment variable or `--set` value becomes a TOML value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Number,
+    /// `true`/`false`, or `1`/`0`.
+    Flag,
+}
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+/// The bot's settings, from `config.toml`, then environment variables, then
+/// `--set key=value` flags, each layer overriding the one before.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub paths: Paths,
+    pub keys: ApiKeys,
+    pub providers: Providers,
+    pub tools: Tools,
+    pub twitter: TwitterLogin,
+    pub publish: Publish,
+    pub storage: StorageLimits,
+    pub budget: Budget,
+    pub critic: Critic,
+    pub style: Style,
+    pub workers: Workers,
+    pub telemetry: Telemetry,
+}
+
+/// Where the other config files and the data live.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Paths {
+    /// Run directories, the run history and the clip queue.
+    pub data_root: PathBuf,
+    pub models: PathBuf,
+    pub sources: PathBuf,
+    pub prompts: PathBuf,
+    pub safety: PathBuf,
+    pub prices: PathBuf,
+    pub schedule: PathBuf,
+    pub seen_tweets: PathBuf,
+    /// Default `runs.sqlite` in the data root.
+    pub run_history: Option<PathBuf>,
+    /// Default `queue.json` in the data root.
+    pub clip_queue: Option<PathBuf>,
+    pub twitter_cookies: PathBuf,
+    /// Saved reddit and Hacker News responses to read instead of the network.
+    pub content_fixtures: Option<PathBuf>,
+    /// Recorded LLM responses, see `providers.llm_replay`.
+    pub llm_fixtures: PathBuf,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths {
+            data_root: PathBuf::from("./tmp"),
+            models: PathBuf::from("models.toml"),
+            sources: PathBuf::from("sources.toml"),
+            prompts: PathBuf::from("prompts"),
+            safety: PathBuf::from("safety.toml"),
+            prices: PathBuf::from("prices.toml"),
+            schedule: PathBuf::from("schedule.toml"),
+            seen_tweets: PathBuf::from("seen_tweets.json"),
+            run_history: None,
+            clip_queue: None,
+            twitter_cookies: PathBuf::from("twitter_cookies.txt"),
+            content_fixtures: None,
+            llm_fixtures: PathBuf::from("fixtures/llm"),
+        }
+    }
+}
+
+impl Paths {
+    pub fn run_history(&self) -> PathBuf {
+        self.run_history
+            .clone()
+            .unwrap_or_else(|| self.data_root.join("runs.sqlite"))
+    }
+
+    pub fn clip_queue(&self) -> PathBuf {
+        self.clip_queue
+            .clone()
+            .unwrap_or_else(|| self.data_root.join("queue.json"))
+    }
+}
+
+/// Provider keys and platform tokens, best kept in the environment or `.env`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeys {
+    pub openai: Option<String>,
+    pub anthropic: Option<String>,
+    pub elevenlabs: Option<String>,
+    pub fal: Option<String>,
+    pub youtube: Option<String>,
+    pub tiktok: Option<String>,
+    pub instagram: Option<String>,
+}
+
+impl ApiKeys {
+    pub fn openai(&self) -> Result<&str> {
+        required(&self.openai, "OPENAI_API_KEY", "keys.openai")
+    }
+
+    pub fn anthropic(&self) -> Result<&str> {
+        required(&self.anthropic, "ANTHROPIC_API_KEY", "keys.anthropic")
+    }
+
+    pub fn elevenlabs(&self) -> Result<&str> {
+        required(&self.elevenlabs, "ELEVENLABS_API_KEY", "keys.elevenlabs")
+    }
+
+    pub fn fal(&self) -> Result<&str> {
+        required(&self.fal, "FAL_API_KEY", "keys.fal")
+    }
+}
+
+// Keys never show up in logs
+impl fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeys")
+            .field("openai", &secret(&self.openai))
+            .field("anthropic", &secret(&self.anthropic))
+            .field("elevenlabs", &secret(&self.elevenlabs))
+            .field("fal", &secret(&self.fal))
+            .field("youtube", &secret(&self.youtube))
+            .field("tiktok", &secret(&self.tiktok))
+            .field("instagram", &secret(&self.instagram))
+            .finish()
+    }
+}
+
+fn secret(value: &Option<String>) -> &'static str {
+    if value.is_some() {
+        "set"
+    } else {
+        "unset"
+    }
+}
+
+fn required<'a>(key: &'a Option<String>, var: &str, name: &str) -> Result<&'a str> {
+    key.as_deref()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| anyhow!("{var} is not set (or {name} in the config)"))
+}
+
+/// Which service each stage uses. The offline ones need no keys or network,
+/// only ffmpeg, and are what `juicero dry-run` uses.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Providers {
+    /// OpenAI compatible endpoint of Ollama, for roles in `models.toml` that use it.
+    pub ollama_url: String,
+    /// Fal queue endpoint of the image model.
+    pub fal_endpoint: String,
+    pub tts: TtsProvider,
+    pub transcription: TranscriptionProvider,
+    pub images: ImageProvider,
+    pub llm_replay: LlmReplay,
+}
+
+impl Default for Providers {
+    fn default() -> Self {
+        Providers {
+            ollama_url: "http://localhost:11434/v1".to_string(),
+            fal_endpoint: "https://queue.fal.run/fal-ai/fast-sdxl".to_string(),
+            tts: TtsProvider::ElevenLabs,
+            transcription: TranscriptionProvider::Whisperx,
+            images: ImageProvider::Fal,
+            llm_replay: LlmReplay::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsProvider {
+    ElevenLabs,
+    /// A quiet tone as long as the text takes to read out.
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionProvider {
+    Whisperx,
+    /// The text's words spread evenly over the audio.
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProvider {
+    Fal,
+    /// A plain coloured frame per image.
+    Placeholder,
+}
+
+/// What LLM calls do with the fixtures in `paths.llm_fixtures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmReplay {
+    /// Call the provider, keep nothing.
+    Off,
+    /// Call the provider and save every response as a fixture.
+    Record,
+    /// Answer from the fixtures, never call the provider.
+    Replay,
+    /// Answer every prompt of a role with `<role>.txt`.
+    Canned,
+}
+
+impl LlmReplay {
+    /// Whether LLM calls reach the providers, and so need their keys.
+    pub fn is_live(self) -> bool {
+        matches!(self, LlmReplay::Off | LlmReplay::Record)
+    }
+}
+
+/// External programs, by name on the `PATH` or full path.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tools {
+    pub ffmpeg: String,
+    pub ffprobe: String,
+    pub docker: String,
+    /// Docker image that runs whisperx for the subtitles.
+    pub whisperx_image: String,
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Tools {
+            ffmpeg: "ffmpeg".to_string(),
+            ffprobe: "ffprobe".to_string(),
+            docker: "docker".to_string(),
+            whisperx_image: "whisperx:large-v3-en".to_string(),
+        }
+    }
+}
+
+/// The X account the scraper logs in as, and posts with if `publish.twitter`.
+/// Without a username and password the X feeds are skipped.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwitterLogin {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Asked for when X wants to confirm the login.
+    pub email: Option<String>,
+    /// TOTP secret, for accounts with 2FA on.
+    pub two_factor_secret: Option<String>,
+}
+
+impl TwitterLogin {
+    /// Username and password, or why they can't be used.
+    pub fn username_password(&self) -> Result<(&str, &str)> {
+        Ok((
+            required(&self.username, "TWITTER_USERNAME", "twitter.username")?,
+            required(&self.password, "TWITTER_PASSWORD", "twitter.password")?,
+        ))
+    }
+}
+
+impl fmt::Debug for TwitterLogin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TwitterLogin")
+            .field("username", &self.username)
+            .field("password", &secret(&self.password))
+            .field("email", &self.email)
+            .field("two_factor_secret", &secret(&self.two_factor_secret))
+            .finish()
+    }
+}
+
+/// Where finished clips get posted, besides the platforms with a token in `keys`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Publish {
+    /// Post on X with the scraper's login.
+    pub twitter: bool,
+    /// Needed with `keys.instagram`.
+    pub instagram_user_id: Option<String>,
+}
+
+/// How long run directories are kept.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageLimits {
+    /// Intermediates (audio, subtitles, images, the unsubtitled slideshow) are
+    /// deleted after this many days. The final video and the reports stay.
+    pub prune_after_days: u64,
+    /// Past this, whole runs are deleted oldest first until the data root fits.
+    pub max_total_mb: Option<u64>,
+}
+
+impl Default for StorageLimits {
+    fn default() -> Self {
+        StorageLimits {
+            prune_after_days: 7,
+            max_total_mb: None,
+        }
+    }
+}
+
+/// Once a day's runs have cost `daily_usd`, the loop pauses until midnight.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Budget {
+    pub daily_usd: Option<f64>,
+}
+
+/// How hard the comedian has to work.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Critic {
+    /// Drafts per round.
+    pub candidates: usize,
+    /// Average score out of 10 a draft needs to win.
+    pub threshold: f32,
+    /// Rounds of revision before we settle for the best draft.
+    pub max_rounds: usize,
+}
+
+impl Default for Critic {
+    fn default() -> Self {
+        Critic {
+            candidates: 3,
+            threshold: 6.5,
+            max_rounds: 2,
+        }
+    }
+}
+
+/// The house style rules a draft is held to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Style {
+    /// Drafts the voice takes longer than this to read out are sent back.
+    pub max_spoken_seconds: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            max_spoken_seconds: 30.0,
+        }
+    }
+}
+
+/// Runs in flight at once, and runs in each pooled stage at once.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Workers {
+    pub runs: usize,
+    pub text: usize,
+    pub tts: usize,
+    pub transcription: usize,
+    pub images: usize,
+    pub render: usize,
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Workers {
+            runs: 3,
+            text: 2,
+            tts: 2,
+            transcription: 1,
+            images: 2,
+            render: 1,
+        }
+    }
+}
+
+/// Logs and metrics.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Telemetry {
+    pub log_format: LogFormat,
+    /// Serve Prometheus metrics here, e.g. `0.0.0.0:9184`. Unset means off.
+    pub metrics_addr: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// The coloured console with the agent banners.
+    #[default]
+    Pretty,
+    /// One JSON object per line with the spans of every event, for servers.
+    Json,
+}
+
+// -----------------------------------------------------------------------------
+// Loading
+// -----------------------------------------------------------------------------
+
+impl Config {
+    /// Load every layer, taking the global flags out of `args` and returning
+    /// the rest:
+    ///
+    /// * `--config <path>`: the file, else `CONFIG_PATH`, else `config.toml`.
+    ///   Only the default may be missing.
+    /// * `--set <key>=<value>`, repeatable, e.g. `--set tools.ffmpeg=/opt/bin/ffmpeg`.
+    pub fn load(args: Vec<String>) -> Result<(Config, Vec<String>)> {
+        Config::load_with_env(args, |var| env::var(var).ok())
+    }
+
+    /// [`Config::load`] with the environment variables read through `env`.
+    fn load_with_env(
+        args: Vec<String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<(Config, Vec<String>)> {
+        let mut path = None;
+        let mut sets = Vec::new();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    path = Some(
+                        args.next()
+                            .ok_or_else(|| anyhow!("--config needs a path"))?,
+                    )
+                }
+                "--set" => sets.push(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--set needs key=value"))?,
+                ),
+                _ => rest.push(arg),
+            }
+        }
+
+        let path = path.or_else(|| env("CONFIG_PATH"));
+        let mut table = match &path {
+            Some(path) => read_table(Path::new(path))?,
+            None if Path::new(DEFAULT_FILE).exists() => read_table(Path::new(DEFAULT_FILE))?,
+            None => toml::Table::new(),
+        };
+        for (var, key, kind) in ENV_OVERRIDES {
+            if let Some(value) = env(var) {
+                let value = parse_value(&value, kind).map_err(|e| anyhow!("{var}: {e}"))?;
+                set_key(&mut table, key, value)?;
+            }
+        }
+        for set in sets {
+            let (key, value) = set
+                .split_once('=')
+                .ok_or_else(|| anyhow!("--set needs key=value, got '{set}'"))?;
+            let kind = ENV_OVERRIDES
+                .iter()
+                .find(|(_, name, _)| *name == key)
+                .map_or(Kind::Text, |(_, _, kind)| *kind);
+            let value = parse_value(value, kind).map_err(|e| anyhow!("--set {key}: {e}"))?;
+            set_key(&mut table, key, value)?;
+        }
+
+        let config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| anyhow!("Invalid config: {e}"))?;
+        Ok((config, rest))
+    }
+
+    /// Check what a cycle needs before the first one starts, reporting every
+    /// problem at once: the keys of the LLM providers `models.toml` uses, the
+    /// ElevenLabs and Fal keys, the Fal endpoint, the X login when posting
+    /// there, the critic, style and worker settings, that `ffmpeg`, `ffprobe` and `docker` run, and
+    /// that docker has the whisperx image. Offline providers and LLM replays
+    /// skip the checks of the service they replace.
+    pub fn validate(&self) -> Result<()> {
+        let providers = &self.providers;
+        let mut problems = Vec::new();
+        if providers.llm_replay.is_live() {
+            match ModelConfig::load(&self.paths.models) {
+                Ok(models) => {
+                    if models.uses(Provider::OpenAI) {
+                        if let Err(e) = self.keys.openai() {
+                            problems.push(e.to_string());
+                        }
+                    }
+                    if models.uses(Provider::Anthropic) {
+                        if let Err(e) = self.keys.anthropic() {
+                            problems.push(e.to_string());
+                        }
+                    }
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        if providers.tts == TtsProvider::ElevenLabs {
+            if let Err(e) = self.keys.elevenlabs() {
+                problems.push(e.to_string());
+            }
+        }
+        if providers.images == ImageProvider::Fal {
+            if let Err(e) = self.keys.fal() {
+                problems.push(e.to_string());
+            }
+            if !providers.fal_endpoint.starts_with("https://") {
+                problems.push(format!(
+                    "providers.fal_endpoint must be an https URL, got '{}'",
+                    providers.fal_endpoint
+                ));
+            }
+        }
+
+        if self.publish.twitter {
+            if let Err(e) = self.twitter.username_password() {
+                problems.push(format!("publish.twitter is on but {e}"));
+            }
+        }
+        if self.keys.instagram.is_some() && self.publish.instagram_user_id.is_none() {
+            problems.push(
+                "INSTAGRAM_ACCESS_TOKEN is set without INSTAGRAM_USER_ID \
+                 (or publish.instagram_user_id in the config)"
+                    .to_string(),
+            );
+        }
+        let critic = &self.critic;
+        if !(0.0..=10.0).contains(&critic.threshold) {
+            problems.push(format!(
+                "critic.threshold must be between 0 and 10, got {}",
+                critic.threshold
+            ));
+        }
+        if self.style.max_spoken_seconds <= 0.0 {
+            problems.push(format!(
+                "style.max_spoken_seconds must be above 0, got {}",
+                self.style.max_spoken_seconds
+            ));
+        }
+        let workers = &self.workers;
+        for (name, size) in [
+            ("critic.candidates", critic.candidates),
+            ("critic.max_rounds", critic.max_rounds),
+            ("workers.runs", workers.runs),
+            ("workers.text", workers.text),
+            ("workers.tts", workers.tts),
+            ("workers.transcription", workers.transcription),
+            ("workers.images", workers.images),
+            ("workers.render", workers.render),
+        ] {
+            if size == 0 {
+                problems.push(format!("{name} must be above 0"));
+            }
+        }
+
+        let mut tools = vec![
+            ("ffmpeg", &self.tools.ffmpeg, "-version"),
+            ("ffprobe", &self.tools.ffprobe, "-version"),
+        ];
+        let whisperx = providers.transcription == TranscriptionProvider::Whisperx;
+        if whisperx {
+            tools.push(("docker", &self.tools.docker, "--version"));
+        }
+        for (name, program, flag) in tools {
+            if let Err(e) = run_check(program, &[flag]) {
+                problems.push(format!("tools.{name}: {e}"));
+            }
+        }
+        let image = &self.tools.whisperx_image;
+        if whisperx {
+            if let Err(e) = run_check(&self.tools.docker, &["image", "inspect", image]) {
+                problems.push(format!(
+                    "tools.whisperx_image: docker doesn't have '{image}', build or pull it ({e})"
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        bail!("Configuration problems:\n  - {}", problems.join("\n  - "))
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read config {}: {e}", path.display()))?;
+    toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))
+}
+
+fn parse_value(value: &str, kind: Kind) -> Result<toml::Value> {
+    Ok(match kind {
+        Kind::Text => toml::Value::String(value.to_string()),
+        Kind::Number => match (value.parse::<i64>(), value.parse::<f64>()) {
+            (Ok(number), _) => toml::Value::Integer(number),
+            (_, Ok(number)) => toml::Value::Float(number),
+            _ => bail!("expected a number, got '{value}'"),
+        },
+        Kind::Flag => match value {
+            "true" | "1" => toml::Value::Boolean(true),
+            "false" | "0" | "" => toml::Value::Boolean(false),
+            _ => bail!("expected true or false, got '{value}'"),
+        },
+    })
+}
+
+/// Set a dotted key like `tools.ffmpeg`, creating its tables on the way.
+fn set_key(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<()> {
+    let (sections, name) = match key.rsplit_once('.') {
+        Some((sections, name)) => (sections.split('.').collect(), name),
+        None => (Vec::new(), key),
+    };
+    let mut table = table;
+    for section in sections {
+        table = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("Can't set '{key}', '{section}' isn't a section"))?;
+    }
+    table.insert(name.to_string(), value);
+    Ok(())
+}
+
+/// Run `program` with `args`, failing with its first line of stderr.
+fn run_check(program: &str, args: &[&str]) -> Result<()> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(anyhow!(
+                "'{program} {}' failed: {}",
+                args.join(" "),
+                stderr.lines().next().unwrap_or_default().trim()
+            ))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(anyhow!("'{program}' not found")),
+        Err(e) => Err(anyhow!("Failed to run '{program}': {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    type Vars<'a> = &'a [(&'a str, &'a str)];
+    type Args<'a> = &'a [&'a str];
+
+    /// A file in the temp dir with `contents`, unique to this test run.
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "bot-config-{}-{}-{name}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// `Config::load` of `file`, the environment `vars` and `args`.
+    fn load(file: &str, vars: Vars<'_>, args: Args<'_>) -> Result<(Config, Vec<String>)> {
+        let path = temp_file("config.toml", file);
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(var, value)| (var.to_string(), value.to_string()))
+            .collect();
+        let mut all = vec!["--config".to_string(), path.display().to_string()];
+        all.extend(args.iter().map(|arg| arg.to_string()));
+        let loaded = Config::load_with_env(all, |var| vars.get(var).cloned());
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn each_layer_overrides_the_one_before() {
+        let file = "[tools]\nffmpeg = \"/file/ffmpeg\"\n";
+        let env = [("FFMPEG_PATH", "/env/ffmpeg")];
+        let set = ["--set", "tools.ffmpeg=/set/ffmpeg"];
+        let cases: [(&str, Vars<'_>, Args<'_>, &str); 5] = [
+            ("", &[], &[], "ffmpeg"),
+            (file, &[], &[], "/file/ffmpeg"),
+            (file, &env, &[], "/env/ffmpeg"),
+            (file, &env, &set, "/set/ffmpeg"),
+            ("", &[], &set, "/set/ffmpeg"),
+        ];
+
+        for (file, vars, args, expected) in cases {
+            let (config, _) = load(file, vars, args).unwrap();
+            assert_eq!(config.tools.ffmpeg, expected, "{file:?} {vars:?} {args:?}");
+        }
+    }
+
+    #[test]
+    fn config_path_comes_from_the_flag_or_the_environment() {
+        let path = temp_file("other.toml", "[tools]\nffprobe = \"/other/ffprobe\"\n");
+        let path = path.display().to_string();
+
+        let from_env = |var: &str| (var == "CONFIG_PATH").then(|| path.clone());
+        let (config, _) = Config::load_with_env(Vec::new(), from_env).unwrap();
+        assert_eq!(config.tools.ffprobe, "/other/ffprobe");
+
+        let missing = Config::load_with_env(
+            vec!["--config".to_string(), format!("{path}.missing")],
+            from_env,
+        );
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("Failed to read config"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn values_take_the_type_of_their_key() {
+        let cases: [(Vars<'_>, Args<'_>); 2] = [
+            (
+                &[
+                    ("DAILY_BUDGET_USD", "5"),
+                    ("CRITIC_THRESHOLD", "7.5"),
+                    ("PIPELINE_RUNS", "4"),
+                    ("PUBLISH_TO_TWITTER", "1"),
+                    ("RETENTION_MAX_TOTAL_MB", "512"),
+                ],
+                &[],
+            ),
+            (
+                &[],
+                &[
+                    "--set",
+                    "budget.daily_usd=5",
+                    "--set",
+                    "critic.threshold=7.5",
+                    "--set",
+                    "workers.runs=4",
+                    "--set",
+                    "publish.twitter=true",
+                    "--set",
+                    "storage.max_total_mb=512",
+                ],
+            ),
+        ];
+
+        for (vars, args) in cases {
+            let (config, _) = load("", vars, args).unwrap();
+            assert_eq!(config.budget.daily_usd, Some(5.0));
+            assert_eq!(config.critic.threshold, 7.5);
+            assert_eq!(config.workers.runs, 4);
+            assert!(config.publish.twitter);
+            assert_eq!(config.storage.max_total_mb, Some(512));
+        }
+    }
+
+    #[test]
+    fn parse_value_by_kind() {
+        let cases = [
+            ("42", Kind::Number, Some(toml::Value::Integer(42))),
+            ("0.25", Kind::Number, Some(toml::Value::Float(0.25))),
+            ("lots", Kind::Number, None),
+            ("true", Kind::Flag, Some(toml::Value::Boolean(true))),
+            ("1", Kind::Flag, Some(toml::Value::Boolean(true))),
+            ("0", Kind::Flag, Some(toml::Value::Boolean(false))),
+            ("", Kind::Flag, Some(toml::Value::Boolean(false))),
+            ("yes", Kind::Flag, None),
+            (
+                "42",
+                Kind::Text,
+                Some(toml::Value::String("42".to_string())),
+            ),
+        ];
+
+        for (value, kind, expected) in cases {
+            assert_eq!(
+                parse_value(value, kind).ok(),
+                expected,
+                "{value:?} as {kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_values_name_where_they_came_from() {
+        let cases: [(Vars<'_>, Args<'_>, &str); 4] = [
+            (
+                &[("PIPELINE_RUNS", "lots")],
+                &[],
+                "PIPELINE_RUNS: expected a number",
+            ),
+            (
+                &[("PUBLISH_TO_TWITTER", "yes")],
+                &[],
+                "PUBLISH_TO_TWITTER: expected true or false",
+            ),
+            (
+                &[],
+                &["--set", "workers.runs=lots"],
+                "--set workers.runs: expected a number",
+            ),
+            (&[], &["--set", "workers.runs"], "--set needs key=value"),
+        ];
+
+        for (vars, args, expected) in cases {
+            let error = load("", vars, args).unwrap_err().to_string();
+            assert!(error.contains(expected), "{error}");
+        }
+    }
+
+    #[test]
+    fn set_key_follows_dotted_keys() {
+        let mut table: toml::Table = toml::from_str("[tools]\nffmpeg = \"ffmpeg\"\n").unwrap();
+
+        set_key(&mut table, "top", toml::Value::Integer(1)).unwrap();
+        set_key(&mut table, "tools.ffprobe", "probe".into()).unwrap();
+        set_key(&mut table, "a.b.c", toml::Value::Boolean(true)).unwrap();
+        let error = set_key(&mut table, "tools.ffmpeg.path", "x".into()).unwrap_err();
+
+        assert_eq!(table["top"].as_integer(), Some(1));
+        assert_eq!(table["tools"]["ffmpeg"].as_str(), Some("ffmpeg"));
+        assert_eq!(table["tools"]["ffprobe"].as_str(), Some("probe"));
+        assert_eq!(table["a"]["b"]["c"].as_bool(), Some(true));
+        assert!(
+            error.to_string().contains("'ffmpeg' isn't a section"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        let cases: [(&str, Args<'_>); 3] = [
+            ("[tools]\nffmpge = \"ffmpeg\"\n", &[]),
+            ("[toolz]\n", &[]),
+            ("", &["--set", "workers.threads=4"]),
+        ];
+
+        for (file, args) in cases {
+            let error = load(file, &[], args).unwrap_err().to_string();
+            assert!(error.contains("unknown field"), "{error}");
+        }
+    }
+
+    #[test]
+    fn other_arguments_are_handed_back() {
+        let (_, rest) = load("", &[], &["gc", "--set", "tools.ffmpeg=f", "--dry-run"]).unwrap();
+        assert_eq!(rest, ["gc", "--dry-run"]);
+
+        let error = load("", &[], &["--set"]).unwrap_err().to_string();
+        assert!(error.contains("--set needs key=value"), "{error}");
+    }
+
+    #[test]
+    fn validate_asks_for_the_keys_of_the_providers_models_toml_uses() {
+        let models = temp_file(
+            "models.toml",
+            "[default]\nprovider = \"ollama\"\nmodel = \"llama3\"\n\n\
+             [roles.critic]\nprovider = \"anthropic\"\nmodel = \"claude-3-5-haiku-latest\"\n",
+        );
+        let mut config = Config::default();
+        config.paths.models = models.clone();
+
+        let error = config.validate().unwrap_err().to_string();
+        fs::remove_file(&models).unwrap();
+        assert!(error.contains("ANTHROPIC_API_KEY"), "{error}");
+        assert!(!error.contains("OPENAI_API_KEY"), "{error}");
+    }
+}
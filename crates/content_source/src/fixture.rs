@@ -0,0 +1,32 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::fs;
+use twitter_scraper::SourceTweet;
+
+use crate::ContentSource;
+
+/// Tweets saved as a JSON array of `SourceTweet`s, handed back as if X had
+/// just returned them. For dry runs and testing without the scraper.
+pub struct TweetFixtureSource {
+    path: String,
+}
+
+impl TweetFixtureSource {
+    pub fn new(path: impl Into<String>) -> Self {
+        TweetFixtureSource { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl ContentSource for TweetFixtureSource {
+    fn name(&self) -> String {
+        format!("tweets from {}", self.path)
+    }
+
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>> {
+        let raw = fs::read_to_string(&self.path)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", self.path))?;
+        serde_json::from_str(&raw)
+            .map_err(|e| anyhow!("Failed to parse tweet fixture {}: {e}", self.path))
+    }
+}
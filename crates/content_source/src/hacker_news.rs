@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::DateTime;
+use reqwest::Client;
+use serde::Deserialize;
+use twitter_scraper::SourceTweet;
+
+use crate::{html_to_text, read_location, ContentSource};
+
+// The Algolia HN API returns the whole front page in one request.
+const FRONT_PAGE_URL: &str = "https://hn.algolia.com/api/v1/search?tags=front_page&hitsPerPage=30";
+const MAX_STORY_TEXT_CHARS: usize = 600;
+
+// -----------------------------------------------------------------------------
+// Data models for the Algolia Hacker News search API
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    hits: Vec<Hit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hit {
+    #[serde(rename = "objectID")]
+    object_id: String,
+    title: Option<String>,
+    url: Option<String>,
+    author: String,
+    points: Option<i64>,
+    num_comments: Option<i64>,
+    created_at_i: i64,
+    story_text: Option<String>,
+}
+
+/// The stories currently on the Hacker News front page.
+pub struct HackerNewsSource {
+    client: Client,
+    location: String,
+}
+
+impl Default for HackerNewsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HackerNewsSource {
+    pub fn new() -> Self {
+        HackerNewsSource {
+            client: Client::new(),
+            location: FRONT_PAGE_URL.to_string(),
+        }
+    }
+
+    /// Read the front page from somewhere else, e.g. a saved fixture file.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = location.into();
+        self
+    }
+}
+
+#[async_trait]
+impl ContentSource for HackerNewsSource {
+    fn name(&self) -> String {
+        "hacker news".to_string()
+    }
+
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>> {
+        let raw = read_location(&self.client, &self.location).await?;
+        parse_front_page(&raw)
+    }
+}
+
+/// Turn an Algolia search response into posts. Hits without a title are skipped.
+pub fn parse_front_page(raw: &str) -> Result<Vec<SourceTweet>> {
+    let response: SearchResponse = serde_json::from_str(raw)
+        .map_err(|e| anyhow!("Failed to parse Hacker News response: {e}, raw: {raw}"))?;
+
+    Ok(response
+        .hits
+        .into_iter()
+        .filter_map(|hit| {
+            let title = hit.title.filter(|t| !t.trim().is_empty())?;
+            let text = match hit.story_text.as_deref().map(html_to_text) {
+                Some(body) if !body.is_empty() => {
+                    let body: String = body.chars().take(MAX_STORY_TEXT_CHARS).collect();
+                    format!("{title}\n{body}")
+                }
+                _ => title,
+            };
+            let discussion = format!("https://news.ycombinator.com/item?id={}", hit.object_id);
+
+            Some(SourceTweet {
+                id: format!("hn:{}", hit.object_id),
+                author: hit.author,
+                author_name: Some("Hacker News".to_string()),
+                created_at: DateTime::from_timestamp(hit.created_at_i, 0),
+                text,
+                likes: hit.points.unwrap_or(0).max(0) as u32,
+                retweets: 0,
+                replies: hit.num_comments.unwrap_or(0).max(0) as u32,
+                views: None,
+                quoted: None,
+                reply_to: None,
+                reply_to_id: None,
+                retweeted_by: None,
+                media: Vec::new(),
+                url: Some(hit.url.unwrap_or(discussion)),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_front_page_fixture() {
+        let stories =
+            parse_front_page(include_str!("../../../fixtures/sources/hacker_news.json")).unwrap();
+
+        assert_eq!(stories.len(), 2);
+        assert_eq!(stories[0].id, "hn:43000001");
+        assert_eq!(stories[0].author, "pg_fan");
+        assert_eq!(stories[0].likes, 812);
+        assert_eq!(stories[0].replies, 304);
+        assert_eq!(
+            stories[0].url.as_deref(),
+            Some("https://example.com/cron-standup")
+        );
+        assert_eq!(
+            stories[0].created_at.unwrap().to_rfc3339(),
+            "2025-02-18T07:50:00+00:00"
+        );
+        // An Ask HN has no url of its own and carries its text
+        assert_eq!(
+            stories[1].url.as_deref(),
+            Some("https://news.ycombinator.com/item?id=43000002")
+        );
+        assert!(stories[1]
+            .text
+            .ends_with("\nWe have 14 services and 3 users, two of which are me."));
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use source_registry::Feed;
+use std::{fs, path::Path};
+use twitter_scraper::{SourceTweet, TwitterSession};
+
+mod fixture;
+mod hacker_news;
+mod reddit;
+mod rss;
+
+pub use fixture::TweetFixtureSource;
+pub use hacker_news::HackerNewsSource;
+pub use reddit::RedditSource;
+pub use rss::RssSource;
+
+// Reddit rejects requests with the default reqwest user agent.
+const USER_AGENT: &str = "brainrotbot/0.1 (+https://github.com/davidbegin/brainrotbot)";
+
+/// Somewhere we can pull the latest posts from to find a topic.
+///
+/// Every source hands back `SourceTweet`s, so the summarizer doesn't care
+/// whether the text came from X, a subreddit or an RSS feed.
+#[async_trait]
+pub trait ContentSource: Send {
+    /// Short label used in logs, e.g. "r/rust".
+    fn name(&self) -> String;
+
+    /// Fetch the latest posts.
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>>;
+}
+
+/// Any X feed (user, search, list, trends, home timeline) through the logged-in session.
+pub struct TwitterSource<'a> {
+    session: &'a mut TwitterSession,
+    feed: Feed,
+}
+
+impl<'a> TwitterSource<'a> {
+    pub fn new(session: &'a mut TwitterSession, feed: Feed) -> Self {
+        TwitterSource { session, feed }
+    }
+}
+
+#[async_trait]
+impl ContentSource for TwitterSource<'_> {
+    fn name(&self) -> String {
+        self.feed.to_string()
+    }
+
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>> {
+        match twitter_scraper::fetch_feed(self.session.scraper_mut(), &self.feed).await {
+            Ok(tweets) => Ok(tweets),
+            // An expired session shows up as a failed fetch: log back in and retry once
+            Err(e) => {
+                if !self.session.ensure_logged_in().await? {
+                    return Err(e);
+                }
+                twitter_scraper::fetch_feed(self.session.scraper_mut(), &self.feed).await
+            }
+        }
+    }
+}
+
+/// Builds the source for a registry feed.
+/// X feeds need `session`; it's an error to ask for one while logged out.
+///
+/// With `fixtures`, reddit and Hacker News read saved responses
+/// (`reddit_<name>.json`, `hacker_news.json`) from that directory instead of the network.
+/// RSS feeds can point at a local file directly.
+pub fn source_for_feed<'a>(
+    feed: &Feed,
+    session: Option<&'a mut TwitterSession>,
+    fixtures: Option<&Path>,
+) -> Result<Box<dyn ContentSource + 'a>> {
+    let fixture = |file: String| fixtures.map(|dir| dir.join(file));
+
+    Ok(match feed {
+        Feed::Rss(location) => Box::new(RssSource::new(location.clone())),
+        Feed::Subreddit(name) => {
+            let source = RedditSource::new(name.clone());
+            match fixture(format!("reddit_{name}.json")) {
+                Some(path) => Box::new(source.with_location(path.to_string_lossy())),
+                None => Box::new(source),
+            }
+        }
+        Feed::HackerNews => {
+            let source = HackerNewsSource::new();
+            match fixture("hacker_news.json".to_string()) {
+                Some(path) => Box::new(source.with_location(path.to_string_lossy())),
+                None => Box::new(source),
+            }
+        }
+        Feed::TweetFixture(path) => Box::new(TweetFixtureSource::new(path.clone())),
+        _ => {
+            let session =
+                session.ok_or_else(|| anyhow!("{feed} needs X but we aren't logged in"))?;
+            Box::new(TwitterSource::new(session, feed.clone()))
+        }
+    })
+}
+
+/// Read a URL, or a local file when `location` isn't http(s).
+/// Local files let every source run against fixtures without the network.
+async fn read_location(client: &Client, location: &str) -> Result<String> {
+    if !location.starts_with("http://") && !location.starts_with("https://") {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        return fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {path}: {e}"));
+    }
+
+    let resp = client
+        .get(location)
+        .header("User-Agent", USER_AGENT)
+        .send()
+        .await?;
+    let status = resp.status();
+    let text = resp.text().await?;
+    if !status.is_success() {
+        bail!("GET {location} failed with status {status}, raw: {text}");
+    }
+    Ok(text)
+}
+
+/// Strip HTML tags and decode entities.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = decode_entities(&text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode the named entities feeds actually use and any numeric one, in one
+/// pass so `&amp;lt;` stays `&lt;`. Anything else is left as it is.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .map(|end| &rest[1..end + 1])
+            .filter(|entity| entity.len() <= 10);
+        match entity.and_then(|entity| Some((entity, entity_char(entity)?))) {
+            Some((entity, c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character `&entity;` stands for, e.g. `amp`, `#39` or `#x2F`.
+fn entity_char(entity: &str) -> Option<char> {
+    let code = match entity {
+        "amp" => return Some('&'),
+        "lt" => return Some('<'),
+        "gt" => return Some('>'),
+        "quot" => return Some('"'),
+        "apos" => return Some('\''),
+        "nbsp" => return Some(' '),
+        _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => entity.strip_prefix('#')?.parse().ok()?,
+        },
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode_entities("it&#39;s &#x2F; &#X2f;"), "it's / /");
+        assert_eq!(decode_entities("&#x1F980; &#8212;"), "🦀 —");
+        // One pass, so an escaped entity stays an entity
+        assert_eq!(decode_entities("&amp;lt;"), "&lt;");
+        assert_eq!(
+            decode_entities("AT&T & co &bogus; &#xZZ;"),
+            "AT&T & co &bogus; &#xZZ;"
+        );
+    }
+
+    #[test]
+    fn strips_tags() {
+        assert_eq!(
+            html_to_text("<p>One&nbsp;<b>two</b></p>\n<p>three</p>"),
+            "One two three"
+        );
+    }
+}
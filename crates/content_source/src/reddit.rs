@@ -0,0 +1,179 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::DateTime;
+use reqwest::Client;
+use serde::Deserialize;
+use twitter_scraper::{Media, MediaKind, SourceTweet};
+
+use crate::{read_location, ContentSource};
+
+const MAX_SELFTEXT_CHARS: usize = 600;
+
+// -----------------------------------------------------------------------------
+// Data models for reddit's public listing JSON (`/r/<name>/hot.json`)
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListingData {
+    children: Vec<Child>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Child {
+    data: Post,
+}
+
+#[derive(Debug, Deserialize)]
+struct Post {
+    id: String,
+    title: String,
+    #[serde(default)]
+    selftext: String,
+    author: String,
+    subreddit: String,
+    #[serde(default)]
+    score: i64,
+    #[serde(default)]
+    num_comments: i64,
+    created_utc: f64,
+    permalink: String,
+    url: Option<String>,
+    #[serde(default)]
+    post_hint: Option<String>,
+    #[serde(default)]
+    stickied: bool,
+    #[serde(default)]
+    over_18: bool,
+}
+
+/// The hot posts of a subreddit. No login needed, just a descriptive user agent.
+pub struct RedditSource {
+    client: Client,
+    subreddit: String,
+    location: String,
+}
+
+impl RedditSource {
+    pub fn new(subreddit: impl Into<String>) -> Self {
+        let subreddit = subreddit.into();
+        RedditSource {
+            client: Client::new(),
+            location: format!("https://www.reddit.com/r/{subreddit}/hot.json?limit=25"),
+            subreddit,
+        }
+    }
+
+    /// Read the listing from somewhere else, e.g. a saved fixture file.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = location.into();
+        self
+    }
+}
+
+#[async_trait]
+impl ContentSource for RedditSource {
+    fn name(&self) -> String {
+        format!("r/{}", self.subreddit)
+    }
+
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>> {
+        let raw = read_location(&self.client, &self.location).await?;
+        parse_listing(&raw)
+    }
+}
+
+/// Turn a listing into posts, skipping pinned mod posts and NSFW ones.
+pub fn parse_listing(raw: &str) -> Result<Vec<SourceTweet>> {
+    let listing: Listing = serde_json::from_str(raw)
+        .map_err(|e| anyhow!("Failed to parse reddit listing: {e}, raw: {raw}"))?;
+
+    Ok(listing
+        .data
+        .children
+        .into_iter()
+        .map(|c| c.data)
+        .filter(|p| !p.stickied && !p.over_18)
+        .map(|p| {
+            let selftext: String = p.selftext.chars().take(MAX_SELFTEXT_CHARS).collect();
+            let text = if selftext.trim().is_empty() {
+                p.title
+            } else {
+                format!("{}\n{}", p.title, selftext.trim())
+            };
+            let media = match (p.post_hint.as_deref(), &p.url) {
+                (Some("image"), Some(url)) => vec![Media {
+                    kind: MediaKind::Photo,
+                    url: url.clone(),
+                    alt_text: None,
+                }],
+                (Some("hosted:video"), Some(url)) => vec![Media {
+                    kind: MediaKind::Video,
+                    url: url.clone(),
+                    alt_text: None,
+                }],
+                _ => Vec::new(),
+            };
+
+            SourceTweet {
+                id: format!("reddit:{}", p.id),
+                author: p.author,
+                author_name: Some(format!("r/{}", p.subreddit)),
+                created_at: DateTime::from_timestamp(p.created_utc as i64, 0),
+                text,
+                // upvotes are the closest thing to likes
+                likes: p.score.max(0) as u32,
+                retweets: 0,
+                replies: p.num_comments.max(0) as u32,
+                views: None,
+                quoted: None,
+                reply_to: None,
+                reply_to_id: None,
+                retweeted_by: None,
+                media,
+                url: Some(format!("https://www.reddit.com{}", p.permalink)),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_listing_fixture() {
+        let posts = parse_listing(include_str!(
+            "../../../fixtures/sources/reddit_programming.json"
+        ))
+        .unwrap();
+
+        // The stickied thread is skipped
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].id, "reddit:1bbbbb");
+        assert_eq!(posts[0].author_name.as_deref(), Some("r/programming"));
+        assert_eq!(posts[0].likes, 4210);
+        assert_eq!(posts[0].replies, 388);
+        assert_eq!(
+            posts[0].text,
+            "Our CI now takes longer than our sprint\n\
+             We added one more lint step and now a green build is a quarterly event."
+        );
+        assert_eq!(
+            posts[0].url.as_deref(),
+            Some("https://www.reddit.com/r/programming/comments/1bbbbb/our_ci_now_takes_longer_than_our_sprint/")
+        );
+        assert!(posts[0].media.is_empty());
+        // An image post keeps its image
+        assert_eq!(
+            posts[1].text,
+            "Mechanical keyboard that autocorrects your commit messages"
+        );
+        assert!(matches!(posts[1].media[0].kind, MediaKind::Photo));
+        assert_eq!(posts[1].media[0].url, "https://i.redd.it/keyboard.jpg");
+    }
+}
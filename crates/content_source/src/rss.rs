@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use feed_rs::{
+    model::{Entry, Text},
+    parser,
+};
+use reqwest::Client;
+use twitter_scraper::SourceTweet;
+
+use crate::{html_to_text, read_location, ContentSource};
+
+// Feed bodies can be whole articles, the summarizer only needs the gist.
+const MAX_BODY_CHARS: usize = 600;
+
+/// An RSS 2.0 or Atom feed, from a URL or a local file.
+pub struct RssSource {
+    client: Client,
+    location: String,
+}
+
+impl RssSource {
+    pub fn new(location: impl Into<String>) -> Self {
+        RssSource {
+            client: Client::new(),
+            location: location.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ContentSource for RssSource {
+    fn name(&self) -> String {
+        format!("rss {}", self.location)
+    }
+
+    async fn fetch(&mut self) -> Result<Vec<SourceTweet>> {
+        let xml = read_location(&self.client, &self.location).await?;
+        parse_feed(&xml)
+    }
+}
+
+/// Parse the items of an RSS or Atom document.
+pub fn parse_feed(xml: &str) -> Result<Vec<SourceTweet>> {
+    // An item without a guid or id is known by its link, not by feed-rs' hash of it
+    let feed = parser::Builder::new()
+        .id_generator(|links, _, _| {
+            links
+                .first()
+                .map(|link| link.href.clone())
+                .unwrap_or_default()
+        })
+        .build()
+        .parse(xml.as_bytes())
+        .map_err(|e| anyhow!("Not an RSS or Atom feed: {e}, raw: {}", truncate(xml, 200)))?;
+
+    let feed_title = feed
+        .title
+        .map(plain_text)
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "rss".to_string());
+
+    Ok(feed
+        .entries
+        .into_iter()
+        .filter_map(|entry| parse_entry(entry, &feed_title))
+        .collect())
+}
+
+fn parse_entry(entry: Entry, feed_title: &str) -> Option<SourceTweet> {
+    let title = entry.title.map(plain_text).unwrap_or_default();
+    let link = entry
+        .links
+        .iter()
+        .find(|l| l.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+        .or(entry.links.first())
+        .map(|l| l.href.clone());
+    // content:encoded or Atom content first, then the description or summary
+    let body = entry
+        .content
+        .and_then(|c| c.body)
+        .or(entry.summary.map(|s| s.content))
+        .map(|html| truncate(&html_to_text(&html), MAX_BODY_CHARS))
+        .unwrap_or_default();
+
+    let text = match (title.is_empty(), body.is_empty()) {
+        (true, true) => return None,
+        (false, true) => title,
+        (true, false) => body,
+        (false, false) => format!("{title}\n{body}"),
+    };
+    if entry.id.is_empty() {
+        return None;
+    }
+
+    let author = entry
+        .authors
+        .into_iter()
+        .map(|a| html_to_text(&a.name))
+        .find(|a| !a.is_empty())
+        .unwrap_or_else(|| feed_title.to_string());
+
+    Some(SourceTweet {
+        id: format!("rss:{}", entry.id),
+        author,
+        author_name: Some(feed_title.to_string()),
+        created_at: entry.published.or(entry.updated),
+        text,
+        likes: 0,
+        retweets: 0,
+        replies: 0,
+        views: None,
+        quoted: None,
+        reply_to: None,
+        reply_to_id: None,
+        retweeted_by: None,
+        media: Vec::new(),
+        url: link,
+    })
+}
+
+/// A title as plain text, Atom allows HTML in them.
+fn plain_text(text: Text) -> String {
+    if text.content_type.to_string().contains("html") {
+        html_to_text(&text.content)
+    } else {
+        text.content.trim().to_string()
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_rss_fixture() {
+        let items = parse_feed(include_str!("../../../fixtures/sources/rss.xml")).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, "rss:https://example.com/posts/llm-toaster");
+        assert_eq!(items[0].author, "Jane Writer");
+        assert_eq!(items[0].author_name.as_deref(), Some("Example Tech News"));
+        assert_eq!(
+            items[0].text,
+            "Startup ships a toaster that runs a language model\n\
+             The toaster asks you to rate your toast before it will pop up."
+        );
+        assert_eq!(
+            items[0].created_at.unwrap().to_rfc3339(),
+            "2025-02-18T14:03:00+00:00"
+        );
+        assert_eq!(
+            items[0].url.as_deref(),
+            Some("https://example.com/posts/llm-toaster")
+        );
+        // No dc:creator, so the feed stands in for the author
+        assert_eq!(items[1].author, "Example Tech News");
+        assert!(items[1]
+            .text
+            .ends_with("the new name & logo \"test well\"."));
+    }
+
+    #[test]
+    fn parses_the_atom_fixture() {
+        let items = parse_feed(include_str!("../../../fixtures/sources/atom.xml")).unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "rss:tag:blog.example.com,2025:yaml-build");
+        assert_eq!(items[0].author, "Sam Builder");
+        assert_eq!(items[0].author_name.as_deref(), Some("Example Dev Blog"));
+        assert_eq!(
+            items[0].text,
+            "We rewrote our build system in YAML and regret everything\n\
+             Indentation is now a production incident."
+        );
+        assert_eq!(
+            items[0].url.as_deref(),
+            Some("https://blog.example.com/yaml-build")
+        );
+        assert_eq!(
+            items[0].created_at.unwrap().to_rfc3339(),
+            "2025-02-18T11:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn decodes_numeric_entities() {
+        let xml = r#"<rss version="2.0"><channel><title>Caf&#233; news</title>
+            <item>
+              <title>Caf&#xE9; opens &#8212; again</title>
+              <link>https://example.com/cafe</link>
+              <description>&lt;p&gt;Rust&amp;#8217;s borrow checker &amp;amp; you&lt;/p&gt;</description>
+            </item>
+            </channel></rss>"#;
+
+        let items = parse_feed(xml).unwrap();
+
+        assert_eq!(items[0].author, "Café news");
+        assert_eq!(
+            items[0].text,
+            "Café opens — again\nRust\u{2019}s borrow checker & you"
+        );
+        // Without a guid the link is the id
+        assert_eq!(items[0].id, "rss:https://example.com/cafe");
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_feed() {
+        assert!(parse_feed("<html><body>Not found</body></html>").is_err());
+    }
+}
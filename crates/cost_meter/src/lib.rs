@@ -0,0 +1,302 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+
+tokio::task_local! {
+    static METER: UsageMeter;
+}
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    InputTokens,
+    OutputTokens,
+    Characters,
+    Images,
+    InferenceSeconds,
+}
+
+impl Unit {
+    /// How many units a price in `prices.toml` is for.
+    fn priced_per(&self) -> f64 {
+        match self {
+            Unit::InputTokens | Unit::OutputTokens => 1_000_000.0,
+            Unit::Characters => 1_000.0,
+            Unit::Images | Unit::InferenceSeconds => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Unit::InputTokens => "input_tokens",
+            Unit::OutputTokens => "output_tokens",
+            Unit::Characters => "characters",
+            Unit::Images => "images",
+            Unit::InferenceSeconds => "inference_seconds",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What one provider call used, e.g. 1200 input tokens of "o3-mini" from "openai".
+#[derive(Debug, Clone)]
+pub struct Usage {
+    pub provider: String,
+    /// The model, voice model or Fal endpoint.
+    pub item: String,
+    pub quantity: f64,
+    pub unit: Unit,
+}
+
+impl Usage {
+    pub fn new(provider: &str, item: &str, quantity: f64, unit: Unit) -> Self {
+        Usage {
+            provider: provider.to_string(),
+            item: item.to_string(),
+            quantity,
+            unit,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Metering
+// -----------------------------------------------------------------------------
+
+/// Collects the usage of everything called inside [`UsageMeter::scope`].
+///
+/// Provider clients call [`record`] without knowing which run they work for,
+/// the run wraps its stages in a scope and takes the usage afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct UsageMeter {
+    usage: Arc<Mutex<Vec<Usage>>>,
+}
+
+impl UsageMeter {
+    pub fn new() -> Self {
+        UsageMeter::default()
+    }
+
+    /// Run `work`, recording into this meter. Tasks spawned inside don't
+    /// inherit the scope, wrap them too.
+    pub async fn scope<F: Future>(&self, work: F) -> F::Output {
+        METER.scope(self.clone(), work).await
+    }
+
+    pub fn push(&self, usage: Usage) {
+        if let Ok(mut all) = self.usage.lock() {
+            all.push(usage);
+        }
+    }
+
+    /// Everything recorded so far, leaving the meter empty.
+    pub fn take(&self) -> Vec<Usage> {
+        self.usage
+            .lock()
+            .map(|mut all| std::mem::take(&mut *all))
+            .unwrap_or_default()
+    }
+}
+
+/// Record usage against the current [`UsageMeter::scope`]. Outside a scope
+/// (demos, one-off commands) the usage is dropped.
+pub fn record(usage: Usage) {
+    let _ = METER.try_with(|meter| meter.push(usage));
+}
+
+/// Rough token count for providers whose client doesn't report usage:
+/// about four characters per token for English.
+pub fn estimate_tokens(text: &str) -> f64 {
+    (text.chars().count() as f64 / 4.0).ceil()
+}
+
+// -----------------------------------------------------------------------------
+// Prices
+// -----------------------------------------------------------------------------
+
+/// `prices.toml`: USD per unit, by provider and item. An item of `"*"` covers
+/// everything of that provider without its own entry.
+#[derive(Debug, Default)]
+pub struct PriceTable {
+    prices: HashMap<String, HashMap<String, HashMap<Unit, f64>>>,
+    warned: Mutex<HashSet<(String, String)>>,
+}
+
+impl PriceTable {
+    /// A missing file prices everything at zero.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            warn!(
+                "No price table at {}, costs will show as $0",
+                path.display()
+            );
+            return Ok(PriceTable::default());
+        }
+        let raw = fs::read_to_string(path)?;
+        let prices =
+            toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))?;
+        Ok(PriceTable {
+            prices,
+            warned: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Estimated USD for `usage`. Units an item has no price for cost nothing,
+    /// items missing from the table are warned about once.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let item = self
+            .prices
+            .get(&usage.provider)
+            .and_then(|items| items.get(&usage.item).or_else(|| items.get("*")));
+        let Some(item) = item else {
+            let key = (usage.provider.clone(), usage.item.clone());
+            if let Ok(mut warned) = self.warned.lock() {
+                if warned.insert(key) {
+                    warn!(
+                        "No price for {} '{}' in the price table, counting it as $0",
+                        usage.provider, usage.item
+                    );
+                }
+            }
+            return 0.0;
+        };
+        item.get(&usage.unit)
+            .map(|price| usage.quantity * price / usage.unit.priced_per())
+            .unwrap_or(0.0)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Budget
+// -----------------------------------------------------------------------------
+
+/// Once a day's runs have cost `daily_usd`, the loop pauses until midnight.
+/// `None` means no limit.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub daily_usd: Option<f64>,
+}
+
+impl Budget {
+    pub fn exceeded(&self, spent_today: f64) -> bool {
+        self.daily_usd.is_some_and(|limit| spent_today >= limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> PriceTable {
+        PriceTable {
+            prices: toml::from_str(toml).unwrap(),
+            warned: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn assert_usd(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "${actual}, expected ${expected}"
+        );
+    }
+
+    const PRICES: &str = r#"
+        [openai."o3-mini"]
+        input_tokens = 1.10
+        output_tokens = 4.40
+
+        [anthropic."*"]
+        input_tokens = 3.00
+        output_tokens = 15.00
+
+        [anthropic."claude-3-5-haiku-latest"]
+        input_tokens = 0.80
+
+        [elevenlabs.eleven_turbo_v2]
+        characters = 0.15
+
+        [fal."fal-ai/fast-sdxl"]
+        images = 0.0025
+    "#;
+
+    #[test]
+    fn prices_are_per_unit_of_their_unit() {
+        let prices = table(PRICES);
+
+        // Tokens per million, characters per thousand, images each
+        let cost = |provider, item, quantity, unit| {
+            prices.cost(&Usage::new(provider, item, quantity, unit))
+        };
+        assert_usd(
+            cost("openai", "o3-mini", 2_000.0, Unit::InputTokens),
+            0.0022,
+        );
+        assert_usd(cost("openai", "o3-mini", 500.0, Unit::OutputTokens), 0.0022);
+        assert_usd(
+            cost("elevenlabs", "eleven_turbo_v2", 1_500.0, Unit::Characters),
+            0.225,
+        );
+        assert_usd(cost("fal", "fal-ai/fast-sdxl", 4.0, Unit::Images), 0.01);
+    }
+
+    #[test]
+    fn star_covers_items_without_their_own_prices() {
+        let prices = table(PRICES);
+
+        let claude = Usage::new("anthropic", "claude-3-7-sonnet", 1e6, Unit::OutputTokens);
+        assert_usd(prices.cost(&claude), 15.0);
+        // An item's own table wins, even for units only "*" prices
+        let haiku = |unit| Usage::new("anthropic", "claude-3-5-haiku-latest", 1e6, unit);
+        assert_usd(prices.cost(&haiku(Unit::InputTokens)), 0.80);
+        assert_usd(prices.cost(&haiku(Unit::OutputTokens)), 0.0);
+    }
+
+    #[test]
+    fn unknown_items_and_units_cost_nothing() {
+        let prices = table(PRICES);
+
+        assert_usd(
+            prices.cost(&Usage::new("openai", "gpt-5", 1e6, Unit::InputTokens)),
+            0.0,
+        );
+        assert_usd(
+            prices.cost(&Usage::new("ollama", "llama3", 1e6, Unit::InputTokens)),
+            0.0,
+        );
+        assert_usd(
+            prices.cost(&Usage::new(
+                "fal",
+                "fal-ai/fast-sdxl",
+                30.0,
+                Unit::InferenceSeconds,
+            )),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn the_budget_is_exceeded_once_spending_reaches_it() {
+        let budget = Budget {
+            daily_usd: Some(5.0),
+        };
+
+        assert!(!budget.exceeded(4.99));
+        assert!(budget.exceeded(5.0));
+        assert!(budget.exceeded(7.5));
+        assert!(!Budget::default().exceeded(1e9));
+    }
+}
@@ -7,17 +7,16 @@ use agent_twitter_client::{
 };
 use anyhow::{anyhow, Result};
 use chrono::Utc;
+use cost_meter::{Unit, Usage};
 use elevenlabs_rs::utils::play;
 use elevenlabs_rs::{ElevenLabsClient, Model, PreMadeVoiceID, TextToSpeech, TextToSpeechBody};
+use run_workspace::RunWorkspace;
 use std::{env, fs};
+use tracing::{info_span, warn, Instrument};
 
-/// Saves TTS audio to `./tmp/<timestamp>.mp3` using ElevenLabs.
-/// Requires the environment variable `ELEVENLABS_API_KEY`.
-///
-pub async fn save_tts_audio(id: &str, text: &str) -> Result<String> {
-    let api_key =
-        env::var("ELEVENLABS_API_KEY").expect("Missing ELEVENLABS_API_KEY environment variable");
-
+/// Saves TTS audio to the run's `tts.mp3` using ElevenLabs.
+/// The characters sent are reported to the current usage meter.
+pub async fn save_tts_audio(workspace: &RunWorkspace, api_key: &str, text: &str) -> Result<String> {
     let client = ElevenLabsClient::new(api_key);
 
     // Use default voice settings via our pre-made voice.
@@ -28,7 +27,24 @@ pub async fn save_tts_audio(id: &str, text: &str) -> Result<String> {
     let tts_body = TextToSpeechBody::new(text, model.clone());
     let tts_endpoint = TextToSpeech::new(voice_id, tts_body);
 
-    let bytes = client.hit(tts_endpoint).await.map_err(|e| anyhow!(e))?;
+    let bytes = client
+        .hit(tts_endpoint)
+        .instrument(info_span!(
+            "tts",
+            provider = "elevenlabs",
+            model = "eleven_turbo_v2"
+        ))
+        .await
+        .map_err(|e| {
+            pipeline_metrics::provider_error("elevenlabs");
+            anyhow!(e)
+        })?;
+    cost_meter::record(Usage::new(
+        "elevenlabs",
+        "eleven_turbo_v2",
+        text.chars().count() as f64,
+        Unit::Characters,
+    ));
 
     // Optionally, play the audio.
     play(bytes.clone()).map_err(|e| anyhow!(e))?;
@@ -36,14 +52,22 @@ pub async fn save_tts_audio(id: &str, text: &str) -> Result<String> {
     // fs::create_dir_all("./tmp")?;
     // let filename = format!("{}.mp3", Utc::now().timestamp());
 
-    fs::create_dir_all(format!("./tmp/{}", id))?;
-    let filename = format!("{}.mp3", "tts");
-    let local_audio_path = format!("./tmp/{}/{}", id, filename);
+    let local_audio_path = workspace.tts_audio();
 
     fs::write(&local_audio_path, &bytes).map_err(|e| {
-        eprintln!("Error writing TTS file: {e:?}");
+        warn!("Error writing TTS file: {e:?}");
         anyhow!(e)
     })?;
+    workspace.record(
+        &local_audio_path,
+        "audio",
+        &[
+            ("provider", "elevenlabs"),
+            ("model", "eleven_turbo_v2"),
+            ("voice", "Ethan"),
+            ("text", text),
+        ],
+    )?;
 
-    Ok(local_audio_path)
+    Ok(local_audio_path.to_string_lossy().to_string())
 }
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use chrono::Utc;
+use cost_meter::{Unit, Usage};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -29,6 +30,16 @@ pub struct FalOutput {
     pub has_nsfw_concepts: Option<Vec<bool>>,
 }
 
+impl FalOutput {
+    /// Whether Fal's safety checker flagged image `index`.
+    pub fn is_nsfw(&self, index: usize) -> bool {
+        self.has_nsfw_concepts
+            .as_ref()
+            .and_then(|flags| flags.get(index).copied())
+            .unwrap_or(false)
+    }
+}
+
 /// Represents each image object in the "images" array.
 #[derive(Debug, Deserialize)]
 pub struct ImageInfo {
@@ -57,26 +68,19 @@ struct GenerateImageRequest<'a> {
     prompt: &'a str,
 }
 
-/// Run the full flow:
+/// Run the full flow for `prompt` on the queue `endpoint`, e.g.
+/// `https://queue.fal.run/fal-ai/fast-sdxl`:
 /// 1) Generate image request
 /// 2) Poll until completed
 /// 3) Fetch final result
 /// 4) Save raw JSON
 /// 5) Iterate and download each image by URL
 /// 6) Return `FalOutput`
-pub async fn fal_demo() -> Result<FalOutput> {
-    // Load FAL_API_KEY from environment
-    let fal_key = std::env::var("FAL_API_KEY")
-        .map_err(|_| anyhow!("FAL_API_KEY environment variable not set"))?;
-
-    // Example usage:
-    let endpoint = "https://queue.fal.run/fal-ai/fast-sdxl";
-    let prompt = "The coolest turtle ever ALIVE";
-
+pub async fn fal_demo(endpoint: &str, fal_key: &str, prompt: &str) -> Result<FalOutput> {
     println!("Starting job with prompt: {prompt}");
 
     // 1) Submit the generation job (POST)
-    let gen_response = generate_image(endpoint, &fal_key, prompt).await?;
+    let gen_response = generate_image(endpoint, fal_key, prompt).await?;
     println!("Immediate generation response: {gen_response:?}");
 
     // 2) Extract request_id from the immediate response
@@ -94,7 +98,7 @@ pub async fn fal_demo() -> Result<FalOutput> {
 
     for attempt in 1..=max_retries {
         sleep(poll_interval).await;
-        let status_json = get_request_status(&request_id, &fal_key).await?;
+        let status_json = get_request_status(endpoint, &request_id, fal_key).await?;
 
         // Suppose the status JSON has: { "status": "completed" }
         let status_str = status_json
@@ -115,7 +119,7 @@ pub async fn fal_demo() -> Result<FalOutput> {
     }
 
     // 4) Fetch the final result
-    let final_json = get_request_result(&request_id, &fal_key).await?;
+    let final_json = get_request_result(endpoint, &request_id, fal_key).await?;
 
     // 5) Save the raw JSON to disk
     let filename = format!("final_result_{}.json", Utc::now().timestamp());
@@ -124,13 +128,24 @@ pub async fn fal_demo() -> Result<FalOutput> {
 
     // 6) Parse the final JSON into `FalOutput`
     let fal_output: FalOutput = serde_json::from_value(final_json)?;
+    let image_count = fal_output.images.as_ref().map_or(0, Vec::len);
+    cost_meter::record(Usage::new(
+        "fal",
+        "fal-ai/fast-sdxl",
+        image_count as f64,
+        Unit::Images,
+    ));
 
     // 7) Download and save each returned image (if URLs exist)
     if let Some(images) = &fal_output.images {
-        let download_client = build_client(&fal_key)?;
+        let download_client = build_client(fal_key)?;
         let timestamp = Utc::now().timestamp();
 
         for (i, image_info) in images.iter().enumerate() {
+            if fal_output.is_nsfw(i) {
+                eprintln!("Skipping image {i}, Fal flagged it as NSFW");
+                continue;
+            }
             if let Some(url) = &image_info.url {
                 let resp = download_client.get(url).send().await?.error_for_status()?;
                 let image_bytes = resp.bytes().await?;
@@ -182,21 +197,31 @@ pub async fn generate_image(
     Ok(parsed)
 }
 
-/// GET the request status from:
-/// `https://queue.fal.run/fal-ai/lightning-models/requests/$REQUEST_ID/status`
-async fn get_request_status(request_id: &str, fal_key: &str) -> Result<serde_json::Value> {
+/// GET the request status from `$ENDPOINT/requests/$REQUEST_ID/status`
+async fn get_request_status(
+    endpoint: &str,
+    request_id: &str,
+    fal_key: &str,
+) -> Result<serde_json::Value> {
     let client = build_client(fal_key)?;
-    let url = format!("https://queue.fal.run/fal-ai/lightning-models/requests/{request_id}/status");
+    let url = format!(
+        "{}/requests/{request_id}/status",
+        endpoint.trim_end_matches('/')
+    );
 
     let resp = client.get(&url).send().await?.error_for_status()?;
     Ok(resp.json().await?)
 }
 
-/// GET the final result once the request is completed:
-/// `https://queue.fal.run/fal-ai/lightning-models/requests/$REQUEST_ID`
-async fn get_request_result(request_id: &str, fal_key: &str) -> Result<serde_json::Value> {
+/// GET the final result once the request is completed, from
+/// `$ENDPOINT/requests/$REQUEST_ID`
+async fn get_request_result(
+    endpoint: &str,
+    request_id: &str,
+    fal_key: &str,
+) -> Result<serde_json::Value> {
     let client = build_client(fal_key)?;
-    let url = format!("https://queue.fal.run/fal-ai/lightning-models/requests/{request_id}");
+    let url = format!("{}/requests/{request_id}", endpoint.trim_end_matches('/'));
 
     let resp = client.get(&url).send().await?.error_for_status()?;
     Ok(resp.json().await?)
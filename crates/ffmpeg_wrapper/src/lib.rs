@@ -1,19 +1,25 @@
 use anyhow::{anyhow, Result};
 use chrono::Utc;
+use run_workspace::RunWorkspace;
 use std::process::Command;
+use tracing::{info, info_span, warn};
 
-/// Builds a slideshow MP4 from images described in `filelist.txt` plus the audio track.
-pub fn create_video_from_filelist_and_audio(run_id: i64, audio_path: &str) -> Result<()> {
+/// Builds a slideshow MP4 from images described in `filelist.txt` plus the audio track,
+/// with `ffmpeg` as the ffmpeg binary. Returns the path of the subtitled `final_output.mp4`.
+pub fn create_video_from_filelist_and_audio(
+    workspace: &RunWorkspace,
+    audio_path: &str,
+    ffmpeg: &str,
+) -> Result<String> {
+    let run_id = workspace.run_id();
     let timestamp = chrono::Local::now().timestamp();
-    let output_file = format!("./tmp/{}/{}.mp4", run_id, timestamp);
+    let output_file = workspace.slideshow(timestamp).to_string_lossy().to_string();
 
-    println!("\n~~~ Creating Video ~~~");
-    println!("\tRun ID: {}", run_id);
-    println!("\tAudio Path: {}", audio_path);
-    println!("\tOutput File: {}\n", output_file);
+    let _span = info_span!("render", provider = "ffmpeg").entered();
+    info!(run_id, audio_path, output_file, "Creating video");
 
-    let filelist = format!("./tmp/{}/filelist.txt", run_id);
-    let srt_file = format!("./tmp/{}/tts.ass", run_id);
+    let filelist = workspace.filelist().to_string_lossy().to_string();
+    let srt_file = workspace.subtitles_ass().to_string_lossy().to_string();
     // let srt_file = format!("./tmp/{}/tts.srt", run_id);
 
     let filter = format!("pad=ceil(iw/2)*2:ceil(ih/2)*2");
@@ -43,26 +49,29 @@ pub fn create_video_from_filelist_and_audio(run_id: i64, audio_path: &str) -> Re
         &output_file,
     ];
 
-    println!("Running ffmpeg command:");
-    println!("ffmpeg {}", args.join(" "));
+    info!("Running ffmpeg {}", args.join(" "));
 
-    let status = Command::new("ffmpeg").args(&args).status()?;
+    let status = Command::new(ffmpeg).args(&args).status()?;
 
     if !status.success() {
         return Err(anyhow!("ffmpeg failed with status: {:?}", status));
     }
+    workspace.record(
+        &output_file,
+        "render",
+        &[("filelist", &filelist), ("audio", audio_path)],
+    )?;
 
-    let final_output = format!("./tmp/{}/final_output.mp4", run_id);
+    let final_output = workspace.final_video().to_string_lossy().to_string();
     let subtitle_filter = format!("ass={}", srt_file);
 
     // This subtitle file isn't working
-    println!("Running ffmpeg command:");
-    println!(
-        "ffmpeg -y -i {} -vf {} -c:a copy -loglevel error {}",
+    info!(
+        "Running ffmpeg -y -i {} -vf {} -c:a copy -loglevel error {}",
         output_file, subtitle_filter, final_output
     );
 
-    let status = Command::new("ffmpeg")
+    let status = Command::new(ffmpeg)
         .args(&[
             "-y",
             "-i",
@@ -80,14 +89,114 @@ pub fn create_video_from_filelist_and_audio(run_id: i64, audio_path: &str) -> Re
     if !status.success() {
         return Err(anyhow!("ffmpeg failed with status: {:?}", status));
     }
-    println!("Created slideshow: final_output.mp4");
-    Ok(())
+    workspace.record(
+        &final_output,
+        "render",
+        &[("video", &output_file), ("subtitles", &srt_file)],
+    )?;
+    info!("Created slideshow: final_output.mp4");
+    Ok(final_output)
+}
+
+/// Writes `seconds` of a quiet tone to the run's `tts.mp3`, standing in for
+/// the TTS voice on offline runs. Returns the path.
+pub fn save_placeholder_audio(
+    workspace: &RunWorkspace,
+    ffmpeg: &str,
+    seconds: f32,
+) -> Result<String> {
+    let audio_path = workspace.tts_audio().to_string_lossy().to_string();
+    let source = format!("sine=frequency=220:duration={seconds:.2},volume=0.1");
+    let status = Command::new(ffmpeg)
+        .args([
+            "-y",
+            "-f",
+            "lavfi",
+            "-i",
+            &source,
+            "-loglevel",
+            "error",
+            &audio_path,
+        ])
+        .status()?;
+    if !status.success() {
+        return Err(anyhow!("ffmpeg failed with status: {:?}", status));
+    }
+    workspace.record(
+        &audio_path,
+        "audio",
+        &[
+            ("provider", "offline"),
+            ("seconds", &format!("{seconds:.2}")),
+        ],
+    )?;
+    Ok(audio_path)
+}
+
+/// A plain 1024x1024 PNG in `color` (a name or `0xRRGGBB`), standing in for a
+/// generated image on offline runs.
+pub fn placeholder_image(ffmpeg: &str, color: &str) -> Result<Vec<u8>> {
+    let source = format!("color=c={color}:s=1024x1024");
+    let output = Command::new(ffmpeg)
+        .args([
+            "-f",
+            "lavfi",
+            "-i",
+            &source,
+            "-frames:v",
+            "1",
+            "-f",
+            "image2pipe",
+            "-c:v",
+            "png",
+            "-loglevel",
+            "error",
+            "-",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed with status: {:?}, {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Length of a media file in seconds, with `ffprobe`. Fails on files it can't
+/// read, so it doubles as a check that a render is a valid video.
+pub fn probe_duration(ffprobe: &str, path: &str) -> Result<f32> {
+    let output = Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffprobe couldn't read {path}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let duration = String::from_utf8_lossy(&output.stdout);
+    duration.trim().parse().map_err(|_| {
+        anyhow!(
+            "ffprobe gave no duration for {path}, got '{}'",
+            duration.trim()
+        )
+    })
 }
 
 fn add_subtitles_to_video(video_file: &str, subtitle_file: &str, output_file: &str) {
     // Example ffmpeg command:
     // ffmpeg -i input.mp4 -vf subtitles=subs.srt -c:a copy output.mp4
-    println!("Subtitles Time");
+    info!("Subtitles Time");
     // "subtitles={}:force_style='PrimaryColour=&H00FFFF&'",
     let status = Command::new("ffmpeg")
         .args(&[
@@ -103,8 +212,8 @@ fn add_subtitles_to_video(video_file: &str, subtitle_file: &str, output_file: &s
         .expect("Failed to execute ffmpeg");
 
     if status.success() {
-        println!("Subtitles added to {}", output_file);
+        info!("Subtitles added to {}", output_file);
     } else {
-        eprintln!("Failed to add subtitles to {}", video_file);
+        warn!("Failed to add subtitles to {}", video_file);
     }
 }
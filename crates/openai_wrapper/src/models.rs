@@ -0,0 +1,515 @@
+use anyhow::{anyhow, bail, Result};
+use cost_meter::{Unit, Usage};
+use rig::{
+    agent::AgentBuilder,
+    completion::{Completion, CompletionModel, ModelChoice},
+    providers,
+};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::replay::{fixture_key, Cassette, LlmFixture, ReplayMode};
+
+// Anthropic requires max_tokens on every request.
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u64 = 4096;
+const OLLAMA_DEFAULT_URL: &str = "http://localhost:11434/v1";
+// One retry with the parse error usually gets a model back on track.
+const JSON_ATTEMPTS: usize = 2;
+
+/// The jobs we hand to an LLM. Each can use its own provider and model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Turns a pile of tweets into a topic.
+    Summarizer,
+    /// Merges the writer's base prompt with the topic.
+    PromptMerger,
+    /// Writes the actual tweet.
+    Comedian,
+    /// Scores the comedian's drafts.
+    Critic,
+    /// Turns lines of the script into image prompts.
+    VisualDirector,
+    /// Screens topics and tweets before they go anywhere.
+    Moderator,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Role::Summarizer => "summarizer",
+            Role::PromptMerger => "prompt_merger",
+            Role::Comedian => "comedian",
+            Role::Critic => "critic",
+            Role::VisualDirector => "visual_director",
+            Role::Moderator => "moderator",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    OpenAI,
+    Anthropic,
+    /// Local models through Ollama's OpenAI compatible endpoint.
+    Ollama,
+}
+
+impl Provider {
+    /// The name used in `models.toml`, metrics, prices and fixture keys.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Ollama => "ollama",
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Provider, model and sampling settings for one role.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoleModel {
+    pub provider: Provider,
+    pub model: String,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+}
+
+impl RoleModel {
+    /// OpenAI's reasoning models (o1, o3, o4-mini, ...) reject a temperature.
+    fn is_reasoning(&self) -> bool {
+        self.provider == Provider::OpenAI
+            && ["o1", "o3", "o4"].iter().any(|o| self.model.starts_with(o))
+    }
+
+    /// The temperature to send, left out for models that reject one.
+    fn temperature(&self) -> Option<f64> {
+        self.temperature.filter(|_| !self.is_reasoning())
+    }
+}
+
+impl Default for RoleModel {
+    fn default() -> Self {
+        RoleModel {
+            provider: Provider::OpenAI,
+            model: "o3-mini".to_string(),
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+}
+
+/// The on-disk shape of `models.toml`: a default plus per-role overrides.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelConfig {
+    #[serde(default)]
+    pub default: RoleModel,
+    #[serde(default)]
+    pub roles: HashMap<Role, RoleModel>,
+}
+
+impl ModelConfig {
+    /// Load from `path`. A missing file means every role uses the default.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(ModelConfig::default());
+        }
+        let raw = fs::read_to_string(path)?;
+        toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))
+    }
+
+    pub fn for_role(&self, role: Role) -> &RoleModel {
+        self.roles.get(&role).unwrap_or(&self.default)
+    }
+
+    /// Whether the default or any role runs on `provider`.
+    pub fn uses(&self, provider: Provider) -> bool {
+        self.default.provider == provider || self.roles.values().any(|r| r.provider == provider)
+    }
+}
+
+/// Keys and endpoints of the LLM providers. Only those of the providers in
+/// `models.toml` are needed.
+#[derive(Clone, Default)]
+pub struct ProviderKeys {
+    pub openai: Option<String>,
+    pub anthropic: Option<String>,
+    /// Default `http://localhost:11434/v1`.
+    pub ollama_url: Option<String>,
+}
+
+/// One client per configured provider, and the model settings for each role.
+pub struct Models {
+    config: ModelConfig,
+    openai: Option<providers::openai::Client>,
+    anthropic: Option<providers::anthropic::Client>,
+    ollama: Option<providers::openai::Client>,
+    cassette: Cassette,
+}
+
+impl Models {
+    /// Reads the models file at `path` and builds a client with `keys` for
+    /// every provider it mentions. `cassette` records or replays the calls.
+    pub fn load(path: &Path, keys: &ProviderKeys, cassette: Cassette) -> Result<Self> {
+        if cassette.mode() != ReplayMode::Off {
+            info!(
+                "LLM calls in {:?} mode, fixtures in {}",
+                cassette.mode(),
+                cassette.dir().display()
+            );
+        }
+        Models::with_cassette(ModelConfig::load(path)?, keys, cassette)
+    }
+
+    pub fn new(config: ModelConfig, keys: &ProviderKeys) -> Result<Self> {
+        Models::with_cassette(config, keys, Cassette::off())
+    }
+
+    /// Answer every prompt from the fixtures in `dir`. Needs no API keys.
+    pub fn replaying(config: ModelConfig, dir: impl Into<PathBuf>) -> Result<Self> {
+        Models::with_cassette(config, &ProviderKeys::default(), Cassette::replay(dir))
+    }
+
+    /// Answer every prompt of a role with `<role>.txt` from `dir`. Needs no API keys.
+    pub fn canned(config: ModelConfig, dir: impl Into<PathBuf>) -> Result<Self> {
+        Models::with_cassette(config, &ProviderKeys::default(), Cassette::canned(dir))
+    }
+
+    pub fn with_cassette(
+        config: ModelConfig,
+        keys: &ProviderKeys,
+        cassette: Cassette,
+    ) -> Result<Self> {
+        let mut openai = None;
+        let mut anthropic = None;
+        let mut ollama = None;
+        // Replays never reach a provider, so don't demand keys for them
+        let live = !matches!(cassette.mode(), ReplayMode::Replay | ReplayMode::Canned);
+
+        if live && config.uses(Provider::OpenAI) {
+            let key = required(&keys.openai, "OPENAI_API_KEY")?;
+            openai = Some(providers::openai::Client::new(key));
+        }
+        if live && config.uses(Provider::Anthropic) {
+            let key = required(&keys.anthropic, "ANTHROPIC_API_KEY")?;
+            anthropic = Some(providers::anthropic::ClientBuilder::new(key).build());
+        }
+        if live && config.uses(Provider::Ollama) {
+            let url = keys.ollama_url.as_deref().unwrap_or(OLLAMA_DEFAULT_URL);
+            // Ollama ignores the key, but the OpenAI client wants one
+            ollama = Some(providers::openai::Client::from_url("ollama", url));
+        }
+
+        for (role, model) in &config.roles {
+            if model.model.trim().is_empty() {
+                bail!("models.toml: role '{role}' has an empty model name");
+            }
+        }
+        let roles = config
+            .roles
+            .iter()
+            .map(|(role, model)| (role.to_string(), model));
+        for (role, model) in roles.chain([("default".to_string(), &config.default)]) {
+            if model.temperature.is_some() && model.temperature().is_none() {
+                warn!(
+                    "models.toml: {} doesn't take a temperature, leaving it out for '{role}'",
+                    model.model
+                );
+            }
+        }
+
+        Ok(Models {
+            config,
+            openai,
+            anthropic,
+            ollama,
+            cassette,
+        })
+    }
+
+    pub fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    /// Run one prompt for `role` with the given system preamble.
+    ///
+    /// Goes through the cassette: replayed from a fixture, or recorded after the call.
+    /// Live calls report the provider's token counts to the current usage meter,
+    /// estimated from the text if it sent none. Replays cost nothing.
+    pub async fn prompt(&self, role: Role, preamble: &str, prompt: &str) -> Result<String> {
+        let settings = self.config.for_role(role);
+        let model = format!("{}/{}", settings.provider.as_str(), settings.model);
+        let key = fixture_key(&model, preamble, prompt);
+        if let Some(response) = self.cassette.lookup(role, &key)? {
+            return Ok(response);
+        }
+
+        let span = info_span!(
+            "llm",
+            provider = %settings.provider,
+            model = %settings.model,
+            role = %role
+        );
+        let provider = settings.provider.as_str();
+        let reply = self
+            .call_provider(role, preamble, prompt)
+            .instrument(span)
+            .await
+            .inspect_err(|_| pipeline_metrics::provider_error(provider))?;
+        let response = reply.text;
+        let (input_tokens, output_tokens) = reply.tokens.unwrap_or_else(|| {
+            (
+                cost_meter::estimate_tokens(preamble) + cost_meter::estimate_tokens(prompt),
+                cost_meter::estimate_tokens(&response),
+            )
+        });
+        cost_meter::record(Usage::new(
+            provider,
+            &settings.model,
+            input_tokens,
+            Unit::InputTokens,
+        ));
+        cost_meter::record(Usage::new(
+            provider,
+            &settings.model,
+            output_tokens,
+            Unit::OutputTokens,
+        ));
+        self.cassette.store(&LlmFixture {
+            key,
+            provider: provider.to_string(),
+            model: settings.model.clone(),
+            preamble: preamble.to_string(),
+            prompt: prompt.to_string(),
+            response: response.clone(),
+        })?;
+        Ok(response)
+    }
+
+    async fn call_provider(&self, role: Role, preamble: &str, prompt: &str) -> Result<Reply> {
+        let settings = self.config.for_role(role);
+        let missing = || anyhow!("No {} client for role '{role}'", settings.provider);
+
+        match settings.provider {
+            Provider::OpenAI => {
+                let client = self.openai.as_ref().ok_or_else(missing)?;
+                run(client.agent(&settings.model), settings, preamble, prompt).await
+            }
+            Provider::Ollama => {
+                let client = self.ollama.as_ref().ok_or_else(missing)?;
+                run(client.agent(&settings.model), settings, preamble, prompt).await
+            }
+            Provider::Anthropic => {
+                let client = self.anthropic.as_ref().ok_or_else(missing)?;
+                let settings = RoleModel {
+                    max_tokens: settings.max_tokens.or(Some(ANTHROPIC_DEFAULT_MAX_TOKENS)),
+                    ..settings.clone()
+                };
+                run(client.agent(&settings.model), &settings, preamble, prompt).await
+            }
+        }
+    }
+
+    /// Run one prompt for `role` and parse the reply as `T`.
+    ///
+    /// The JSON schema of `T` is appended to the preamble. This works the same on
+    /// every provider and keeps the role's temperature and max tokens, which rig's
+    /// tool-call based extractors don't.
+    pub async fn prompt_json<T: JsonSchema + DeserializeOwned>(
+        &self,
+        role: Role,
+        preamble: &str,
+        prompt: &str,
+    ) -> Result<T> {
+        let schema = serde_json::to_string(&schemars::schema_for!(T))?;
+        let preamble = format!(
+            "{preamble}\n\nReply with only a JSON object, no prose and no code fences, \
+             matching this JSON schema:\n{schema}"
+        );
+
+        let mut request = prompt.to_string();
+        let mut last_error = anyhow!("no attempts made");
+        for _ in 0..JSON_ATTEMPTS {
+            let response = self.prompt(role, &preamble, &request).await?;
+            match parse_json(&response) {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    warn!("{role} replied with bad JSON: {e}");
+                    request = format!(
+                        "{prompt}\n\nYour last reply did not match the schema ({e}). \
+                         Reply again with only the JSON object."
+                    );
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+/// Models like to wrap JSON in prose or code fences, parse the outermost object.
+fn parse_json<T: DeserializeOwned>(response: &str) -> Result<T> {
+    let json = response
+        .find('{')
+        .zip(response.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &response[start..=end])
+        .ok_or_else(|| anyhow!("No JSON object in response, raw: {response}"))?;
+    serde_json::from_str(json).map_err(|e| anyhow!("{e}, raw: {response}"))
+}
+
+/// A model's answer and the input and output tokens its provider counted.
+struct Reply {
+    text: String,
+    tokens: Option<(f64, f64)>,
+}
+
+/// The token counts in a provider's raw response.
+trait TokenCounts {
+    /// Input and output tokens, `None` if the provider didn't send them.
+    fn tokens(&self) -> Option<(f64, f64)>;
+}
+
+impl TokenCounts for providers::openai::CompletionResponse {
+    // Also Ollama's, which may leave usage out
+    fn tokens(&self) -> Option<(f64, f64)> {
+        let usage = self.usage.as_ref()?;
+        let output = usage.total_tokens.saturating_sub(usage.prompt_tokens);
+        Some((usage.prompt_tokens as f64, output as f64))
+    }
+}
+
+impl TokenCounts for providers::anthropic::completion::CompletionResponse {
+    fn tokens(&self) -> Option<(f64, f64)> {
+        Some((
+            self.usage.input_tokens as f64,
+            self.usage.output_tokens as f64,
+        ))
+    }
+}
+
+/// Apply the role's settings to an agent builder of any provider and prompt it.
+async fn run<M>(
+    builder: AgentBuilder<M>,
+    settings: &RoleModel,
+    preamble: &str,
+    prompt: &str,
+) -> Result<Reply>
+where
+    M: CompletionModel,
+    M::Response: TokenCounts,
+{
+    let mut builder = builder.preamble(preamble);
+    if let Some(temperature) = settings.temperature() {
+        builder = builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = settings.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    let agent = builder.build();
+    // The completion API rather than `Prompt`, which drops the usage
+    let response = agent.completion(prompt, Vec::new()).await?.send().await?;
+    let text = match response.choice {
+        ModelChoice::Message(text) => text,
+        ModelChoice::ToolCall(name, _) => bail!("Expected a reply, the model called tool '{name}'"),
+    };
+    Ok(Reply {
+        tokens: response.raw_response.tokens(),
+        text,
+    })
+}
+
+fn required<'a>(key: &'a Option<String>, var: &str) -> Result<&'a str> {
+    key.as_deref()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| anyhow!("{var} not set, but models.toml uses its provider"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reasoning_models_get_no_temperature() {
+        let model = |provider, model: &str| RoleModel {
+            provider,
+            model: model.to_string(),
+            temperature: Some(0.7),
+            max_tokens: None,
+        };
+
+        assert_eq!(model(Provider::OpenAI, "o3-mini").temperature(), None);
+        assert_eq!(model(Provider::OpenAI, "o1").temperature(), None);
+        assert_eq!(model(Provider::OpenAI, "o4-mini").temperature(), None);
+        assert_eq!(model(Provider::OpenAI, "gpt-4o").temperature(), Some(0.7));
+        // Only OpenAI's names mean reasoning models
+        assert_eq!(model(Provider::Ollama, "o3-local").temperature(), Some(0.7));
+    }
+
+    #[test]
+    fn openai_usage_splits_the_total() {
+        let response: providers::openai::CompletionResponse =
+            serde_json::from_value(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1700000000,
+                "model": "o3-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "hi" },
+                    "finish_reason": "stop",
+                }],
+                "usage": { "prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150 },
+            }))
+            .unwrap();
+
+        assert_eq!(response.tokens(), Some((120.0, 30.0)));
+    }
+
+    #[test]
+    fn openai_compatible_replies_without_usage_have_no_counts() {
+        let response: providers::openai::CompletionResponse =
+            serde_json::from_value(serde_json::json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1700000000,
+                "model": "llama3",
+                "choices": [],
+            }))
+            .unwrap();
+
+        assert_eq!(response.tokens(), None);
+    }
+
+    #[test]
+    fn anthropic_usage_is_taken_as_is() {
+        let response: providers::anthropic::completion::CompletionResponse =
+            serde_json::from_value(serde_json::json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-5-sonnet-latest",
+                "content": [{ "type": "text", "text": "hi" }],
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": { "input_tokens": 80, "output_tokens": 12 },
+            }))
+            .unwrap();
+
+        assert_eq!(response.tokens(), Some((80.0, 12.0)));
+    }
+}
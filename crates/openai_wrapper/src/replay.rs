@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+use crate::models::Role;
+
+const DEFAULT_FIXTURES_DIR: &str = "fixtures/llm";
+
+/// What to do with completion calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Call the provider, keep nothing.
+    Off,
+    /// Call the provider and save every request/response pair as a fixture.
+    Record,
+    /// Never call the provider. Answer from fixtures and fail on a missing one.
+    Replay,
+    /// Never call the provider. Answer every prompt of a role with the same
+    /// response, `<role>.txt`, whatever the prompt. For dry runs.
+    Canned,
+}
+
+/// One recorded completion, stored as `<key>.json` in the fixtures directory.
+///
+/// The request fields are kept next to the response so a fixture can be read and
+/// edited by hand, and so a replay miss is easy to track down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmFixture {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub preamble: String,
+    pub prompt: String,
+    pub response: String,
+}
+
+/// Records and replays completion calls, keyed by a hash of model + preamble + prompt.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    mode: ReplayMode,
+    dir: PathBuf,
+}
+
+impl Cassette {
+    pub fn new(mode: ReplayMode, dir: impl Into<PathBuf>) -> Self {
+        Cassette {
+            mode,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn off() -> Self {
+        Cassette {
+            mode: ReplayMode::Off,
+            dir: DEFAULT_FIXTURES_DIR.into(),
+        }
+    }
+
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Cassette {
+            mode: ReplayMode::Record,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Cassette {
+            mode: ReplayMode::Replay,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn canned(dir: impl Into<PathBuf>) -> Self {
+        Cassette {
+            mode: ReplayMode::Canned,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The recorded response for this request. In replay and canned mode a
+    /// missing fixture is an error, otherwise it's `None`.
+    pub fn lookup(&self, role: Role, key: &str) -> Result<Option<String>> {
+        if self.mode == ReplayMode::Canned {
+            let path = self.dir.join(format!("{role}.txt"));
+            return fs::read_to_string(&path)
+                .map(Some)
+                .map_err(|_| anyhow!("No canned {role} response {}", path.display()));
+        }
+        if self.mode != ReplayMode::Replay {
+            return Ok(None);
+        }
+        let path = self.path_for(key);
+        let raw = fs::read_to_string(&path).map_err(|_| {
+            anyhow!(
+                "No LLM fixture {} (record one with LLM_REPLAY=record)",
+                path.display()
+            )
+        })?;
+        let fixture: LlmFixture = serde_json::from_str(&raw)
+            .map_err(|e| anyhow!("Failed to parse LLM fixture {}: {e}", path.display()))?;
+        Ok(Some(fixture.response))
+    }
+
+    /// Save the fixture when recording, otherwise do nothing.
+    pub fn store(&self, fixture: &LlmFixture) -> Result<()> {
+        if self.mode != ReplayMode::Record {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path_for(&fixture.key);
+        fs::write(&path, serde_json::to_string_pretty(fixture)?)?;
+        info!("Recorded LLM fixture {}", path.display());
+        Ok(())
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+/// Fixture key: sha256 of model, preamble and prompt. Each part is length
+/// prefixed so moving text from the preamble into the prompt changes the key.
+pub fn fixture_key(model: &str, preamble: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [model, preamble, prompt] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cassette-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn fixture(key: &str) -> LlmFixture {
+        LlmFixture {
+            key: key.to_string(),
+            provider: "openai".to_string(),
+            model: "o3-mini".to_string(),
+            preamble: "Write a topic.".to_string(),
+            prompt: "tweets".to_string(),
+            response: "{\"topic\": \"feelings fridge\"}".to_string(),
+        }
+    }
+
+    #[test]
+    fn keys_hash_every_part_separately() {
+        let key = fixture_key("openai/o3-mini", "preamble", "prompt");
+
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(key, fixture_key("openai/o3-mini", "preamble", "prompt"));
+        assert_ne!(key, fixture_key("openai/gpt-4o", "preamble", "prompt"));
+        assert_ne!(key, fixture_key("openai/o3-mini", "preamble", "prompt!"));
+        // Moving text across the boundary is a different request
+        assert_ne!(fixture_key("m", "ab", "c"), fixture_key("m", "a", "bc"));
+    }
+
+    #[test]
+    fn a_recorded_response_replays() {
+        let dir = temp_dir("round-trip");
+        let key = fixture_key("openai/o3-mini", "Write a topic.", "tweets");
+
+        Cassette::record(&dir).store(&fixture(&key)).unwrap();
+        let replayed = Cassette::replay(&dir)
+            .lookup(Role::Summarizer, &key)
+            .unwrap();
+
+        assert_eq!(
+            replayed.as_deref(),
+            Some("{\"topic\": \"feelings fridge\"}")
+        );
+        // Recording never answers from the cassette, it always calls the provider
+        assert_eq!(
+            Cassette::record(&dir)
+                .lookup(Role::Summarizer, &key)
+                .unwrap(),
+            None
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_replay_miss_is_an_error() {
+        let dir = temp_dir("miss");
+
+        let error = Cassette::replay(&dir)
+            .lookup(Role::Critic, "0000")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("No LLM fixture"), "{error}");
+        assert!(error.contains("0000.json"), "{error}");
+    }
+
+    #[test]
+    fn only_recording_stores() {
+        let dir = temp_dir("off");
+
+        for cassette in [
+            Cassette::new(ReplayMode::Off, &dir),
+            Cassette::replay(&dir),
+            Cassette::canned(&dir),
+        ] {
+            cassette.store(&fixture("abc")).unwrap();
+        }
+
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn canned_answers_by_role() {
+        let dir = temp_dir("canned");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("critic.txt"), "{\"funny\": 8}").unwrap();
+        let cassette = Cassette::canned(&dir);
+
+        assert_eq!(
+            cassette.lookup(Role::Critic, "any key").unwrap().as_deref(),
+            Some("{\"funny\": 8}")
+        );
+        assert!(cassette.lookup(Role::Comedian, "any key").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use std::net::SocketAddr;
+use tracing::info;
+
+pub const CYCLES_STARTED: &str = "brainrot_cycles_started_total";
+pub const CYCLES_COMPLETED: &str = "brainrot_cycles_completed_total";
+pub const CYCLES_SKIPPED: &str = "brainrot_cycles_skipped_total";
+pub const CYCLES_FAILED: &str = "brainrot_cycles_failed_total";
+pub const LAST_CYCLE: &str = "brainrot_last_cycle_timestamp_seconds";
+pub const STAGE_SECONDS: &str = "brainrot_stage_duration_seconds";
+pub const PROVIDER_ERRORS: &str = "brainrot_provider_errors_total";
+pub const IMAGES_GENERATED: &str = "brainrot_images_generated_total";
+pub const VIDEOS_POSTED: &str = "brainrot_videos_posted_total";
+pub const SPEND: &str = "brainrot_spend_usd";
+pub const SPEND_TODAY: &str = "brainrot_spend_today_usd";
+pub const CLIPS_QUEUED: &str = "brainrot_clips_queued";
+pub const RUNS_IN_FLIGHT: &str = "brainrot_runs_in_flight";
+
+// Stages run from under a second (topic screening) to minutes (images, render).
+const STAGE_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Serve Prometheus metrics on `addr` (e.g. `0.0.0.0:9184`), at any path.
+///
+/// Until this is called the functions below do nothing. Must be called inside
+/// the tokio runtime, the listener runs on it.
+pub fn install(addr: SocketAddr) -> Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets_for_metric(Matcher::Full(STAGE_SECONDS.to_string()), STAGE_BUCKETS)?
+        .install()
+        .map_err(|e| anyhow!("Failed to start the metrics endpoint on {addr}: {e}"))?;
+    describe();
+    info!("Serving metrics on http://{addr}/metrics");
+    Ok(())
+}
+
+fn describe() {
+    describe_counter!(CYCLES_STARTED, "Tweet cycles started");
+    describe_counter!(
+        CYCLES_COMPLETED,
+        "Tweet cycles that rendered a video for the queue"
+    );
+    describe_counter!(
+        CYCLES_SKIPPED,
+        "Tweet cycles skipped for want of new tweets, a topic, or a safe topic"
+    );
+    describe_counter!(
+        CYCLES_FAILED,
+        "Tweet cycles that failed, by the stage that failed"
+    );
+    describe_gauge!(
+        LAST_CYCLE,
+        "Unix time the last cycle finished, whatever its outcome"
+    );
+    describe_histogram!(
+        STAGE_SECONDS,
+        "How long each stage took, by stage and status"
+    );
+    describe_counter!(
+        PROVIDER_ERRORS,
+        "Failed calls to LLM, TTS, image and posting providers"
+    );
+    describe_counter!(IMAGES_GENERATED, "Images kept for a slideshow");
+    describe_counter!(VIDEOS_POSTED, "Videos posted, by platform");
+    describe_gauge!(SPEND, "Estimated USD spent since start");
+    describe_gauge!(
+        SPEND_TODAY,
+        "Estimated USD spent today, as counted for the daily budget"
+    );
+    describe_gauge!(CLIPS_QUEUED, "Rendered clips waiting for a posting slot");
+    describe_gauge!(
+        RUNS_IN_FLIGHT,
+        "Runs past the safety gate still being produced"
+    );
+}
+
+pub fn cycle_started() {
+    counter!(CYCLES_STARTED).increment(1);
+}
+
+pub fn cycle_completed(now: i64) {
+    counter!(CYCLES_COMPLETED).increment(1);
+    gauge!(LAST_CYCLE).set(now as f64);
+}
+
+pub fn cycle_skipped(now: i64) {
+    counter!(CYCLES_SKIPPED).increment(1);
+    gauge!(LAST_CYCLE).set(now as f64);
+}
+
+pub fn cycle_failed(stage: &str, now: i64) {
+    counter!(CYCLES_FAILED, "stage" => stage.to_string()).increment(1);
+    gauge!(LAST_CYCLE).set(now as f64);
+}
+
+pub fn stage_finished(stage: &str, seconds: f64, ok: bool) {
+    let status = if ok { "ok" } else { "failed" };
+    histogram!(STAGE_SECONDS, "stage" => stage.to_string(), "status" => status).record(seconds);
+}
+
+/// `provider` is e.g. "openai", "elevenlabs", "fal" or a publishing platform.
+pub fn provider_error(provider: &str) {
+    counter!(PROVIDER_ERRORS, "provider" => provider.to_string()).increment(1);
+}
+
+pub fn image_generated() {
+    counter!(IMAGES_GENERATED).increment(1);
+}
+
+pub fn video_posted(platform: &str) {
+    counter!(VIDEOS_POSTED, "platform" => platform.to_string()).increment(1);
+}
+
+/// Spend only goes up, but `metrics` counters count whole numbers, so it's a
+/// gauge, named without the counters' `_total`.
+pub fn spent(usd: f64) {
+    gauge!(SPEND).increment(usd);
+}
+
+pub fn spent_today(usd: f64) {
+    gauge!(SPEND_TODAY).set(usd);
+}
+
+pub fn clips_queued(clips: usize) {
+    gauge!(CLIPS_QUEUED).set(clips as f64);
+}
+
+pub fn runs_in_flight(runs: usize) {
+    gauge!(RUNS_IN_FLIGHT).set(runs as f64);
+}
@@ -0,0 +1,346 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path, str::FromStr, time::Duration};
+use tracing::warn;
+
+mod queue;
+mod shutdown;
+
+pub use queue::{Clip, ClipQueue};
+pub use shutdown::Shutdown;
+
+// -----------------------------------------------------------------------------
+// schedule.toml
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct ScheduleFile {
+    #[serde(default = "default_timezone")]
+    timezone: String,
+    #[serde(default = "default_buffer")]
+    buffer: usize,
+    #[serde(default)]
+    min_spacing_minutes: u64,
+    #[serde(default)]
+    windows: Vec<WindowFile>,
+    #[serde(default)]
+    platforms: HashMap<String, PlatformLimits>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowFile {
+    cron: String,
+    timezone: Option<String>,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_buffer() -> usize {
+    1
+}
+
+/// Per-platform limits, `[platforms.<name>]` keyed by `Publisher::platform()`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PlatformLimits {
+    pub max_per_day: Option<u32>,
+}
+
+// -----------------------------------------------------------------------------
+// Schedule
+// -----------------------------------------------------------------------------
+
+/// Cron slots in a timezone, e.g. `0 0 9-21 * * Mon-Fri` for weekdays on the hour.
+#[derive(Debug, Clone)]
+struct Window {
+    cron: cron::Schedule,
+    timezone: Tz,
+}
+
+/// When rendered clips may be posted, and how many are rendered ahead.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    timezone: Tz,
+    buffer: usize,
+    min_spacing: Duration,
+    windows: Vec<Window>,
+    platforms: HashMap<String, PlatformLimits>,
+}
+
+impl Default for Schedule {
+    /// No windows, no limits: every clip is posted as soon as it is rendered.
+    fn default() -> Self {
+        Schedule {
+            timezone: Tz::UTC,
+            buffer: default_buffer(),
+            min_spacing: Duration::ZERO,
+            windows: Vec::new(),
+            platforms: HashMap::new(),
+        }
+    }
+}
+
+impl Schedule {
+    /// A missing file posts every clip as soon as it is rendered.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            warn!(
+                "No schedule at {}, posting clips as soon as they are rendered",
+                path.display()
+            );
+            return Ok(Schedule::default());
+        }
+        let raw = fs::read_to_string(path)?;
+        let file: ScheduleFile =
+            toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))?;
+
+        let timezone = parse_timezone(&file.timezone)?;
+        let windows = file
+            .windows
+            .iter()
+            .map(|window| {
+                let cron = cron::Schedule::from_str(&window.cron).map_err(|e| {
+                    anyhow!("Invalid cron '{}' in {}: {e}", window.cron, path.display())
+                })?;
+                let timezone = match &window.timezone {
+                    Some(name) => parse_timezone(name)?,
+                    None => timezone,
+                };
+                Ok(Window { cron, timezone })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Schedule {
+            timezone,
+            buffer: file.buffer.max(1),
+            min_spacing: Duration::from_secs(file.min_spacing_minutes * 60),
+            windows,
+            platforms: file.platforms,
+        })
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// How many rendered clips to keep waiting for slots, at least one.
+    pub fn buffer(&self) -> usize {
+        self.buffer
+    }
+
+    /// The latest slot at or before `now` that nothing has been posted for
+    /// since `last_used`. Without windows every moment is a slot. Slots missed
+    /// while busy or down are only made up once.
+    pub fn due_slot(
+        &self,
+        now: DateTime<Utc>,
+        last_used: Option<DateTime<Utc>>,
+    ) -> Option<DateTime<Utc>> {
+        let slot = if self.windows.is_empty() {
+            now
+        } else {
+            self.windows
+                .iter()
+                .filter_map(|w| {
+                    // `after` is exclusive, step to the next whole second so a slot
+                    // of exactly `now` counts
+                    let from = DateTime::from_timestamp(now.timestamp() + 1, 0)?;
+                    w.cron.after(&from.with_timezone(&w.timezone)).next_back()
+                })
+                .map(|slot| slot.with_timezone(&Utc))
+                .max()?
+        };
+        match last_used {
+            Some(used) if used >= slot => None,
+            _ => Some(slot),
+        }
+    }
+
+    /// The first slot after `now`, `None` without windows.
+    pub fn next_slot(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.windows
+            .iter()
+            .filter_map(|w| w.cron.after(&now.with_timezone(&w.timezone)).next())
+            .map(|slot| slot.with_timezone(&Utc))
+            .min()
+    }
+
+    /// How much longer `min_spacing_minutes` holds off the next post, if at all.
+    pub fn spacing_left(
+        &self,
+        now: DateTime<Utc>,
+        last_post: Option<DateTime<Utc>>,
+    ) -> Option<Duration> {
+        let since = (now - last_post?).to_std().unwrap_or_default();
+        self.min_spacing
+            .checked_sub(since)
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Midnight of `now`'s day in the schedule's timezone, where daily limits reset.
+    pub fn start_of_day(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&self.timezone);
+        self.timezone
+            .from_local_datetime(&local.date_naive().and_time(NaiveTime::MIN))
+            .earliest()
+            .map(|midnight| midnight.with_timezone(&Utc))
+            .unwrap_or(now)
+    }
+
+    /// Midnight of the day after `now`'s, which may be 23 or 25 hours away.
+    pub fn start_of_next_day(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.start_of_day(self.start_of_day(now) + chrono::Duration::hours(36))
+    }
+
+    /// Whether `platform` may post again after `posted_today` posts.
+    pub fn allows(&self, platform: &str, posted_today: u32) -> bool {
+        self.platforms
+            .get(platform)
+            .and_then(|limits| limits.max_per_day)
+            .is_none_or(|max| posted_today < max)
+    }
+}
+
+fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse()
+        .map_err(|_| anyhow!("Unknown timezone '{name}', expected e.g. 'America/Denver'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn schedule(toml: &str) -> Schedule {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "schedule-{}-{}.toml",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, toml).unwrap();
+        let schedule = Schedule::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        schedule
+    }
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    #[test]
+    fn a_slot_exactly_at_now_is_due_once() {
+        let schedule = schedule("[[windows]]\ncron = \"0 0 9 * * *\"\n");
+        let now = at("2026-05-01T09:00:00Z");
+
+        assert_eq!(schedule.due_slot(now, None), Some(now));
+        assert_eq!(schedule.due_slot(now, Some(now)), None);
+        assert_eq!(schedule.next_slot(now), Some(at("2026-05-02T09:00:00Z")));
+    }
+
+    #[test]
+    fn missed_slots_are_made_up_only_once() {
+        let schedule =
+            schedule("[[windows]]\ncron = \"0 0 9 * * *\"\n[[windows]]\ncron = \"0 0 12 * * *\"\n");
+        let last_used = Some(at("2026-05-01T08:00:00Z"));
+
+        // Down from before 9:00 until 15:00, only the 12:00 slot is made up
+        let now = at("2026-05-01T15:00:00Z");
+        assert_eq!(
+            schedule.due_slot(now, last_used),
+            Some(at("2026-05-01T12:00:00Z"))
+        );
+        assert_eq!(schedule.due_slot(now, Some(now)), None);
+        assert_eq!(
+            schedule.due_slot(at("2026-05-01T11:59:59Z"), last_used),
+            Some(at("2026-05-01T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn a_window_can_override_the_timezone() {
+        let schedule = schedule(
+            "timezone = \"UTC\"\n[[windows]]\ncron = \"0 0 9 * * *\"\ntimezone = \"America/Denver\"\n",
+        );
+
+        // 9:00 in Denver is 15:00 UTC during daylight saving time
+        assert_eq!(schedule.timezone(), Tz::UTC);
+        assert_eq!(
+            schedule.next_slot(at("2026-05-01T09:00:00Z")),
+            Some(at("2026-05-01T15:00:00Z"))
+        );
+        assert_eq!(
+            schedule.due_slot(at("2026-05-01T15:00:00Z"), None),
+            Some(at("2026-05-01T15:00:00Z"))
+        );
+        assert_eq!(
+            schedule.due_slot(at("2026-05-01T14:59:59Z"), None),
+            Some(at("2026-04-30T15:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn without_windows_every_moment_is_a_slot() {
+        let schedule = Schedule::default();
+        let now = at("2026-05-01T03:17:00Z");
+
+        assert_eq!(
+            schedule.due_slot(now, Some(at("2026-05-01T03:00:00Z"))),
+            Some(now)
+        );
+        assert_eq!(schedule.next_slot(now), None);
+    }
+
+    #[test]
+    fn spacing_holds_off_only_until_it_has_passed() {
+        let schedule = schedule("min_spacing_minutes = 30\n");
+        let now = at("2026-05-01T12:00:00Z");
+
+        assert_eq!(
+            schedule.spacing_left(now, Some(at("2026-05-01T11:50:00Z"))),
+            Some(Duration::from_secs(20 * 60))
+        );
+        assert_eq!(
+            schedule.spacing_left(now, Some(at("2026-05-01T11:30:00Z"))),
+            None
+        );
+        assert_eq!(schedule.spacing_left(now, None), None);
+    }
+
+    #[test]
+    fn days_start_at_local_midnight_across_daylight_saving_changes() {
+        let schedule = schedule("timezone = \"America/Denver\"\n");
+
+        // 1 November 2026 is 25 hours long in Denver: midnight is MDT, the
+        // next one MST
+        let early = at("2026-11-01T06:30:00Z");
+        let late = at("2026-11-02T06:30:00Z");
+        assert_eq!(schedule.start_of_day(early), at("2026-11-01T06:00:00Z"));
+        assert_eq!(schedule.start_of_day(late), at("2026-11-01T06:00:00Z"));
+        assert_eq!(
+            schedule.start_of_next_day(early),
+            at("2026-11-02T07:00:00Z")
+        );
+        assert_eq!(schedule.start_of_next_day(late), at("2026-11-02T07:00:00Z"));
+
+        // 8 March 2026 is 23 hours long
+        let spring = at("2026-03-08T12:00:00Z");
+        assert_eq!(schedule.start_of_day(spring), at("2026-03-08T07:00:00Z"));
+        assert_eq!(
+            schedule.start_of_next_day(spring),
+            at("2026-03-09T06:00:00Z")
+        );
+    }
+
+    #[test]
+    fn max_per_day_limits_only_its_platform() {
+        let schedule = schedule("[platforms.tiktok]\nmax_per_day = 2\n");
+
+        assert!(schedule.allows("tiktok", 1));
+        assert!(!schedule.allows("tiktok", 2));
+        assert!(schedule.allows("youtube", 100));
+    }
+}
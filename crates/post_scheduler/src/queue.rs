@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// A rendered video waiting for a posting slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clip {
+    pub run_id: i64,
+    pub video_path: String,
+    pub caption: String,
+    pub title: String,
+    /// Unix timestamp the render finished.
+    pub rendered_at: i64,
+    /// Slots in a row where every platform failed to take it.
+    #[serde(default)]
+    pub failed_slots: u32,
+}
+
+/// Rendered clips in the order they get posted, persisted as JSON so a
+/// restart doesn't lose what was rendered ahead.
+pub struct ClipQueue {
+    path: PathBuf,
+    clips: VecDeque<Clip>,
+}
+
+impl ClipQueue {
+    /// Load the queue from `path`, starting empty if the file doesn't exist yet.
+    /// Clips whose video has since been cleaned up are dropped.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut clips: VecDeque<Clip> = if path.exists() {
+            let raw = fs::read_to_string(&path)?;
+            serde_json::from_str(&raw)
+                .map_err(|e| anyhow!("Failed to parse clip queue {}: {e}", path.display()))?
+        } else {
+            VecDeque::new()
+        };
+        clips.retain(|clip| {
+            let exists = Path::new(&clip.video_path).exists();
+            if !exists {
+                warn!(
+                    "Dropping queued run {}, its video {} is gone",
+                    clip.run_id, clip.video_path
+                );
+            }
+            exists
+        });
+
+        Ok(ClipQueue { path, clips })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.clips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+
+    /// The runs whose clips are waiting, gc has to leave them alone.
+    pub fn run_ids(&self) -> HashSet<i64> {
+        self.clips.iter().map(|clip| clip.run_id).collect()
+    }
+
+    /// The clip that gets the next slot.
+    pub fn front(&self) -> Option<&Clip> {
+        self.clips.front()
+    }
+
+    /// Queue `clip` behind the others and write the queue back to disk.
+    pub fn push(&mut self, clip: Clip) -> Result<()> {
+        self.clips.push_back(clip);
+        self.save()
+    }
+
+    /// Count a slot where every platform failed the next clip, returning how
+    /// many it has failed so far.
+    pub fn record_failure(&mut self) -> Result<u32> {
+        let Some(clip) = self.clips.front_mut() else {
+            return Ok(0);
+        };
+        clip.failed_slots += 1;
+        let failed = clip.failed_slots;
+        self.save()?;
+        Ok(failed)
+    }
+
+    /// Take the next clip off the queue and write the queue back to disk.
+    pub fn pop(&mut self) -> Result<Option<Clip>> {
+        let clip = self.clips.pop_front();
+        self.save()?;
+        Ok(clip)
+    }
+
+    /// Write to a temp file first so a crash can't truncate the queue.
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&self.clips)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(dir: &Path, run_id: i64) -> Clip {
+        let video_path = dir.join(format!("{run_id}.mp4"));
+        fs::write(&video_path, "video").unwrap();
+        Clip {
+            run_id,
+            video_path: video_path.display().to_string(),
+            caption: "caption".to_string(),
+            title: "title".to_string(),
+            rendered_at: run_id,
+            failed_slots: 0,
+        }
+    }
+
+    #[test]
+    fn failures_are_counted_on_the_next_clip_and_saved() {
+        let dir = std::env::temp_dir().join(format!("clip-queue-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queue.json");
+        let mut queue = ClipQueue::load(&path).unwrap();
+        queue.push(clip(&dir, 1)).unwrap();
+        queue.push(clip(&dir, 2)).unwrap();
+
+        assert_eq!(queue.record_failure().unwrap(), 1);
+        assert_eq!(queue.record_failure().unwrap(), 2);
+
+        let reloaded = ClipQueue::load(&path).unwrap();
+        assert_eq!(reloaded.front().unwrap().failed_slots, 2);
+        assert_eq!(reloaded.run_ids(), HashSet::from([1, 2]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clips_queued_before_the_count_start_at_zero() {
+        let raw = r#"{"run_id": 1, "video_path": "v.mp4", "caption": "c", "title": "t", "rendered_at": 1}"#;
+        let clip: Clip = serde_json::from_str(raw).unwrap();
+        assert_eq!(clip.failed_slots, 0);
+    }
+}
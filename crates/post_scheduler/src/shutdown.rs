@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+use tracing::warn;
+
+/// SIGINT/SIGTERM handling. The first signal asks the loop to stop once the
+/// current run is done, the second stops the run where it is.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    signals: watch::Receiver<u32>,
+}
+
+impl Shutdown {
+    /// Start listening for signals. Must be called inside the tokio runtime.
+    pub fn listen() -> Result<Self> {
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        let (sender, signals) = watch::channel(0);
+
+        tokio::spawn(async move {
+            let mut received = 0;
+            loop {
+                tokio::select! {
+                    _ = interrupt.recv() => {}
+                    _ = terminate.recv() => {}
+                }
+                received += 1;
+                if received == 1 {
+                    warn!("Shutting down after the current run, signal again to stop it now");
+                } else {
+                    warn!("Stopping the current run now");
+                }
+                if sender.send(received).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Shutdown { signals })
+    }
+
+    /// Whether a signal has come in, so no new work should start.
+    pub fn requested(&self) -> bool {
+        *self.signals.borrow() >= 1
+    }
+
+    /// Resolves on the second signal, when the current run should be given up.
+    pub async fn forced(&self) {
+        self.signalled(2).await
+    }
+
+    /// Sleep for `duration`, waking early on the first signal.
+    pub async fn sleep(&self, duration: Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {}
+            _ = self.signalled(1) => {}
+        }
+    }
+
+    async fn signalled(&self, count: u32) {
+        let mut signals = self.signals.clone();
+        if signals
+            .wait_for(|received| *received >= count)
+            .await
+            .is_err()
+        {
+            // The listener is gone, no more signals will come
+            std::future::pending::<()>().await;
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, bail, Result};
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Every prompt version directory has to provide these.
+pub const TEMPLATE_NAMES: [&str; 4] = ["summarizer", "prompt_merger", "writer", "critic"];
+
+/// `library.toml`: which prompt versions are in use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LibraryConfig {
+    pub versions: Vec<String>,
+    /// Fixed RNG seed for persona picks, for reproducible runs. Random when unset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// A voice the comedian writes in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String,
+    /// Sections this persona writes for. Empty means every section.
+    #[serde(default)]
+    pub sections: Vec<String>,
+    pub description: String,
+    #[serde(default)]
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PersonaFile {
+    #[serde(default)]
+    personas: Vec<Persona>,
+}
+
+/// What a run used, stamped into `prompts.json` in its run directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptStamp {
+    pub version: String,
+    pub persona: String,
+    pub section: String,
+}
+
+/// Prompt templates and personas loaded from a directory:
+///
+/// ```text
+/// prompts/
+///   library.toml      versions = ["v1", "v2"]
+///   personas.toml     [[personas]] ...
+///   v1/summarizer.txt, v1/prompt_merger.txt, v1/writer.txt, v1/critic.txt
+///   v2/...
+/// ```
+pub struct PromptLibrary {
+    root: PathBuf,
+    versions: Vec<String>,
+    // version -> template name -> template text
+    templates: HashMap<String, HashMap<String, String>>,
+    personas: Vec<Persona>,
+    cycle: usize,
+    rng: StdRng,
+}
+
+impl PromptLibrary {
+    /// Load and check every version listed in `library.toml`, so a missing
+    /// template fails at startup rather than halfway through a cycle.
+    pub fn load(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let config: LibraryConfig = read_toml(&root.join("library.toml"))?;
+        if config.versions.is_empty() {
+            bail!("{}/library.toml lists no versions", root.display());
+        }
+
+        let mut templates = HashMap::new();
+        for version in &config.versions {
+            let mut set = HashMap::new();
+            for name in TEMPLATE_NAMES {
+                let path = root.join(version).join(format!("{name}.txt"));
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read prompt {}: {e}", path.display()))?;
+                set.insert(name.to_string(), text.trim().to_string());
+            }
+            templates.insert(version.clone(), set);
+        }
+
+        let personas_path = root.join("personas.toml");
+        let personas = if personas_path.exists() {
+            read_toml::<PersonaFile>(&personas_path)?.personas
+        } else {
+            Vec::new()
+        };
+
+        Ok(PromptLibrary {
+            root,
+            versions: config.versions,
+            templates,
+            personas,
+            cycle: 0,
+            rng: match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_os_rng(),
+            },
+        })
+    }
+
+    /// Seed the persona picks, so replayed runs send the same prompts every time.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn versions(&self) -> &[String] {
+        &self.versions
+    }
+
+    /// The prompts for the next cycle in `section`.
+    ///
+    /// Versions take turns, so with two versions listed every other cycle uses
+    /// each. The persona is picked at random from the ones for this section.
+    pub fn next_prompts(&mut self, section: &str) -> PromptSet {
+        let version = self.versions[self.cycle % self.versions.len()].clone();
+        self.cycle += 1;
+
+        let matching: Vec<&Persona> = self
+            .personas
+            .iter()
+            .filter(|p| p.sections.iter().any(|s| s == section))
+            .collect();
+        let general: Vec<&Persona> = self
+            .personas
+            .iter()
+            .filter(|p| p.sections.is_empty())
+            .collect();
+        let candidates = if matching.is_empty() {
+            general
+        } else {
+            matching
+        };
+        let persona = candidates
+            .choose(&mut self.rng)
+            .map(|p| (*p).clone())
+            .unwrap_or_else(default_persona);
+
+        PromptSet {
+            templates: self.templates[&version].clone(),
+            version,
+            persona,
+            section: section.to_string(),
+        }
+    }
+}
+
+/// The templates, version and persona picked for one cycle.
+#[derive(Debug, Clone)]
+pub struct PromptSet {
+    pub version: String,
+    pub persona: Persona,
+    pub section: String,
+    templates: HashMap<String, String>,
+}
+
+impl PromptSet {
+    pub fn stamp(&self) -> PromptStamp {
+        PromptStamp {
+            version: self.version.clone(),
+            persona: self.persona.name.clone(),
+            section: self.section.clone(),
+        }
+    }
+
+    /// Fill in template `name`. Fails on a `{{variable}}` we don't have a value for,
+    /// e.g. `{{topic}}` in the summarizer, which runs before there is a topic.
+    pub fn render(&self, name: &str, topic: Option<&str>) -> Result<String> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| anyhow!("No '{name}' template in prompt version {}", self.version))?;
+
+        let examples = self
+            .persona
+            .examples
+            .iter()
+            .map(|e| format!("- {e}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // {{topic}}, {{section}}, {{persona}} and {{examples}}
+        render(template, |var| match var {
+            "topic" => topic.map(str::to_string),
+            "section" => Some(self.section.clone()),
+            "persona" => Some(self.persona.description.clone()),
+            "examples" => Some(examples.clone()),
+            _ => None,
+        })
+        .map_err(|e| anyhow!("Prompt {}/{name}: {e}", self.version))
+    }
+}
+
+/// Replace every `{{name}}` using `lookup`.
+fn render(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| anyhow!("unclosed {{{{ in template"))?;
+        let name = after[..end].trim();
+        let value = lookup(name).ok_or_else(|| anyhow!("no value for {{{{{name}}}}}"))?;
+        out.push_str(&value);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn default_persona() -> Persona {
+    Persona {
+        name: "default".to_string(),
+        sections: Vec::new(),
+        description: "a very online comedian".to_string(),
+        examples: Vec::new(),
+    }
+}
+
+fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let raw =
+        fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+    toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))
+}
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::{read_video, PostRequest, PostResult, Privacy, Publisher};
+
+const DEFAULT_BASE_URL: &str = "https://graph.facebook.com/v21.0";
+const MAX_STATUS_POLLS: usize = 60;
+
+// -----------------------------------------------------------------------------
+// Data models for the Instagram Graph API (Reels, resumable upload)
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct Container {
+    id: String,
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContainerStatus {
+    status_code: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Published {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Permalink {
+    permalink: Option<String>,
+}
+
+/// Publishes Reels to an Instagram professional account via the Graph API.
+/// Needs a long-lived access token and the IG user id.
+pub struct InstagramPublisher {
+    client: Client,
+    base_url: String,
+    access_token: String,
+    user_id: String,
+    poll_interval: Duration,
+}
+
+impl InstagramPublisher {
+    pub fn new(access_token: impl Into<String>, user_id: impl Into<String>) -> Self {
+        InstagramPublisher {
+            client: Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            access_token: access_token.into(),
+            user_id: user_id.into(),
+            poll_interval: Duration::from_secs(3),
+        }
+    }
+
+    /// Point the publisher at a different host, e.g. a local HTTP stand-in.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Send a request and parse the JSON body, surfacing Graph API errors.
+    async fn send_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        req: reqwest::RequestBuilder,
+        what: &str,
+    ) -> Result<T> {
+        let resp = req.send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            bail!("Instagram {what} failed, status: {status}, raw: {text}");
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse Instagram {what} response: {e}, raw: {text}"))
+    }
+
+    /// Step 1: Create a REELS media container with a resumable upload slot.
+    async fn create_container(&self, caption: &str) -> Result<Container> {
+        let req = self
+            .client
+            .post(self.url(&format!("{}/media", self.user_id)))
+            .query(&[
+                ("media_type", "REELS"),
+                ("upload_type", "resumable"),
+                ("caption", caption),
+                ("access_token", &self.access_token),
+            ]);
+        self.send_json(req, "create container").await
+    }
+
+    /// Step 2: Push the bytes to the rupload URI handed back with the container.
+    async fn upload(&self, upload_uri: &str, bytes: Vec<u8>) -> Result<()> {
+        let req = self
+            .client
+            .post(upload_uri)
+            .header("Authorization", format!("OAuth {}", self.access_token))
+            .header("offset", "0")
+            .header("file_size", bytes.len().to_string())
+            .body(bytes);
+        let _: serde_json::Value = self.send_json(req, "upload").await?;
+        Ok(())
+    }
+
+    /// Step 3: Poll the container until Instagram has processed the video.
+    async fn wait_until_ready(&self, container_id: &str) -> Result<()> {
+        for _ in 0..MAX_STATUS_POLLS {
+            tokio::time::sleep(self.poll_interval).await;
+
+            let req = self.client.get(self.url(container_id)).query(&[
+                ("fields", "status_code,status"),
+                ("access_token", &self.access_token),
+            ]);
+            let status: ContainerStatus = self.send_json(req, "container status").await?;
+
+            match status.status_code.as_deref() {
+                Some("FINISHED") => return Ok(()),
+                Some("ERROR") | Some("EXPIRED") => bail!(
+                    "Instagram container {container_id} failed: {}",
+                    status.status.unwrap_or_default()
+                ),
+                // IN_PROGRESS or not reported yet
+                _ => continue,
+            }
+        }
+        bail!("Instagram container {container_id} not ready after {MAX_STATUS_POLLS} polls")
+    }
+
+    /// Step 4: Publish the processed container, returning the media id.
+    async fn publish_container(&self, container_id: &str) -> Result<String> {
+        let req = self
+            .client
+            .post(self.url(&format!("{}/media_publish", self.user_id)))
+            .query(&[
+                ("creation_id", container_id),
+                ("access_token", &self.access_token),
+            ]);
+        let published: Published = self.send_json(req, "media publish").await?;
+        Ok(published.id)
+    }
+
+    /// Best effort: look up the public permalink for the new media.
+    async fn permalink(&self, media_id: &str) -> Option<String> {
+        let req = self.client.get(self.url(media_id)).query(&[
+            ("fields", "permalink"),
+            ("access_token", &self.access_token),
+        ]);
+        let permalink: Permalink = self.send_json(req, "permalink").await.ok()?;
+        permalink.permalink
+    }
+}
+
+#[async_trait]
+impl Publisher for InstagramPublisher {
+    fn platform(&self) -> &'static str {
+        "instagram"
+    }
+
+    async fn publish(&self, request: &PostRequest) -> Result<PostResult> {
+        // The Graph API has no visibility setting, a Reel is seen by everyone
+        if request.metadata.privacy != Privacy::Public {
+            bail!(
+                "Instagram Reels are always public, can't post it {:?}",
+                request.metadata.privacy
+            );
+        }
+        let bytes = read_video(&request.video_path).await?;
+        let container = self.create_container(&request.caption).await?;
+        let upload_uri = container
+            .uri
+            .as_deref()
+            .ok_or_else(|| anyhow!("Instagram container has no upload uri"))?;
+
+        self.upload(upload_uri, bytes).await?;
+        self.wait_until_ready(&container.id).await?;
+        let media_id = self.publish_container(&container.id).await?;
+
+        Ok(PostResult {
+            platform: self.platform().to_string(),
+            url: self.permalink(&media_id).await,
+            post_id: media_id,
+        })
+    }
+}
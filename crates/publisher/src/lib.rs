@@ -0,0 +1,139 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::{fmt, sync::Arc};
+use tokio::{fs, sync::Mutex};
+use twitter_scraper::TwitterSession;
+
+mod instagram;
+mod tiktok;
+mod twitter;
+mod youtube;
+
+pub use instagram::InstagramPublisher;
+pub use tiktok::TikTokPublisher;
+pub use twitter::{TweetSession, TwitterPublisher};
+pub use youtube::YouTubePublisher;
+
+/// Who can see the post once it is live.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Privacy {
+    #[default]
+    Public,
+    /// Only with the link. TikTok and Instagram have no such thing and refuse the post.
+    Unlisted,
+    /// Only the account. Instagram Reels are always public, so Instagram refuses it.
+    Private,
+}
+
+/// Optional per-post metadata. Platforms ignore whatever they don't support.
+#[derive(Debug, Clone, Default)]
+pub struct PostMetadata {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub privacy: Privacy,
+}
+
+/// One rendered 9:16 clip plus the text that goes with it.
+#[derive(Debug, Clone)]
+pub struct PostRequest {
+    pub video_path: String,
+    pub caption: String,
+    pub metadata: PostMetadata,
+}
+
+/// What a platform hands back once the upload is accepted.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostResult {
+    pub platform: String,
+    pub post_id: String,
+    pub url: Option<String>,
+}
+
+/// A short-form video platform we can post a finished clip to.
+#[async_trait]
+pub trait Publisher: Send + Sync {
+    /// Short name used in logs, e.g. "youtube".
+    fn platform(&self) -> &'static str;
+
+    /// Upload the video with its caption and metadata, returning the new post id/url.
+    async fn publish(&self, request: &PostRequest) -> Result<PostResult>;
+}
+
+/// The platforms to post to and their credentials, `None` to skip one.
+#[derive(Default)]
+pub struct Accounts {
+    /// The logged-in X session, shared with the X feeds.
+    pub twitter: Option<Arc<Mutex<TwitterSession>>>,
+    pub youtube_token: Option<String>,
+    pub tiktok_token: Option<String>,
+    /// Access token and user id.
+    pub instagram: Option<(String, String)>,
+}
+
+/// Builds a publisher for every platform in `accounts`.
+pub fn publishers(accounts: Accounts) -> Vec<Box<dyn Publisher>> {
+    let mut publishers: Vec<Box<dyn Publisher>> = Vec::new();
+
+    if let Some(session) = accounts.twitter {
+        publishers.push(Box::new(TwitterPublisher::new(session)));
+    }
+    if let Some(token) = accounts.youtube_token {
+        publishers.push(Box::new(YouTubePublisher::new(token)));
+    }
+    if let Some(token) = accounts.tiktok_token {
+        publishers.push(Box::new(TikTokPublisher::new(token)));
+    }
+    if let Some((token, user_id)) = accounts.instagram {
+        publishers.push(Box::new(InstagramPublisher::new(token, user_id)));
+    }
+
+    publishers
+}
+
+/// The video of a post is missing, empty or unreadable, so no platform can
+/// take it and trying again won't help.
+#[derive(Debug)]
+pub struct UnreadableVideo {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for UnreadableVideo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Can't read video {}: {}", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for UnreadableVideo {}
+
+fn unreadable(path: &str, reason: impl fmt::Display) -> anyhow::Error {
+    UnreadableVideo {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+    .into()
+}
+
+/// Read the whole video into memory, failing early on an empty file.
+async fn read_video(path: &str) -> Result<Vec<u8>> {
+    let bytes = fs::read(path).await.map_err(|e| unreadable(path, e))?;
+    if bytes.is_empty() {
+        return Err(unreadable(path, "the file is empty"));
+    }
+    Ok(bytes)
+}
+
+/// Check the video is there and not empty, for uploads that read it themselves.
+async fn check_video(path: &str) -> Result<()> {
+    let metadata = fs::metadata(path).await.map_err(|e| unreadable(path, e))?;
+    if metadata.len() == 0 {
+        return Err(unreadable(path, "the file is empty"));
+    }
+    Ok(())
+}
+
+/// Cut a string down to `max` characters without splitting a char.
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
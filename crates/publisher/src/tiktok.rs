@@ -0,0 +1,246 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{read_video, truncate, PostRequest, PostResult, Privacy, Publisher};
+
+const DEFAULT_BASE_URL: &str = "https://open.tiktokapis.com";
+// TikTok accepts a single chunk for anything up to 64MB.
+const MAX_SINGLE_CHUNK_BYTES: usize = 64 * 1024 * 1024;
+const MAX_TITLE_CHARS: usize = 2200;
+const MAX_STATUS_POLLS: usize = 60;
+
+// -----------------------------------------------------------------------------
+// Data models for the TikTok Content Posting API
+// -----------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct InitRequest {
+    post_info: PostInfo,
+    source_info: SourceInfo,
+}
+
+#[derive(Serialize)]
+struct PostInfo {
+    title: String,
+    privacy_level: &'static str,
+}
+
+#[derive(Serialize)]
+struct SourceInfo {
+    source: &'static str,
+    video_size: usize,
+    chunk_size: usize,
+    total_chunk_count: usize,
+}
+
+#[derive(Serialize)]
+struct StatusRequest<'a> {
+    publish_id: &'a str,
+}
+
+/// Every TikTok response is `{ "data": {...}, "error": { "code": "ok", ... } }`.
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    data: Option<T>,
+    error: Option<ApiError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    code: String,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InitData {
+    publish_id: String,
+    upload_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusData {
+    status: String,
+    fail_reason: Option<String>,
+    // (sic) TikTok's field name
+    #[serde(default)]
+    publicaly_available_post_id: Vec<u64>,
+}
+
+/// Posts videos through TikTok's Content Posting API (direct post, file upload).
+/// Needs a user access token with the `video.publish` scope.
+pub struct TikTokPublisher {
+    client: Client,
+    base_url: String,
+    access_token: String,
+    poll_interval: Duration,
+}
+
+impl TikTokPublisher {
+    pub fn new(access_token: impl Into<String>) -> Self {
+        TikTokPublisher {
+            client: Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            access_token: access_token.into(),
+            poll_interval: Duration::from_secs(3),
+        }
+    }
+
+    /// Point the publisher at a different host, e.g. a local HTTP stand-in.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// POST JSON to a TikTok endpoint and unwrap the `data`/`error` envelope.
+    async fn call<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let resp = self
+            .client
+            .post(self.url(path))
+            .bearer_auth(&self.access_token)
+            .json(body)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let text = resp.text().await?;
+        let envelope: Envelope<T> = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse TikTok response: {e}, raw: {text}"))?;
+
+        if let Some(error) = envelope.error.filter(|e| e.code != "ok") {
+            bail!(
+                "TikTok {path} failed ({status}): {} {}",
+                error.code,
+                error.message.unwrap_or_default()
+            );
+        }
+        envelope
+            .data
+            .ok_or_else(|| anyhow!("TikTok {path} returned no data, raw: {text}"))
+    }
+
+    /// Step 1: Register the post and get an upload URL.
+    async fn init(
+        &self,
+        request: &PostRequest,
+        privacy_level: &'static str,
+        video_size: usize,
+    ) -> Result<InitData> {
+        let body = InitRequest {
+            post_info: PostInfo {
+                title: truncate(&request.caption, MAX_TITLE_CHARS),
+                privacy_level,
+            },
+            source_info: SourceInfo {
+                source: "FILE_UPLOAD",
+                video_size,
+                chunk_size: video_size,
+                total_chunk_count: 1,
+            },
+        };
+        self.call("/v2/post/publish/video/init/", &body).await
+    }
+
+    /// Step 2: PUT the whole file as a single chunk.
+    async fn upload(&self, upload_url: &str, bytes: Vec<u8>) -> Result<()> {
+        let len = bytes.len();
+        let resp = self
+            .client
+            .put(upload_url)
+            .header("Content-Type", "video/mp4")
+            .header("Content-Range", format!("bytes 0-{}/{}", len - 1, len))
+            .body(bytes)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            bail!("TikTok video upload failed, status: {}", resp.status());
+        }
+        Ok(())
+    }
+
+    /// Step 3: Poll until TikTok finishes processing the post.
+    async fn wait_for_publish(&self, publish_id: &str) -> Result<Option<String>> {
+        for _ in 0..MAX_STATUS_POLLS {
+            tokio::time::sleep(self.poll_interval).await;
+
+            let status: StatusData = self
+                .call(
+                    "/v2/post/publish/status/fetch/",
+                    &StatusRequest { publish_id },
+                )
+                .await?;
+
+            match status.status.as_str() {
+                "PUBLISH_COMPLETE" => {
+                    return Ok(status
+                        .publicaly_available_post_id
+                        .first()
+                        .map(|id| id.to_string()));
+                }
+                "FAILED" => bail!(
+                    "TikTok publish {publish_id} failed: {}",
+                    status.fail_reason.unwrap_or_default()
+                ),
+                // PROCESSING_UPLOAD, PROCESSING_DOWNLOAD, SEND_TO_USER_INBOX...
+                _ => continue,
+            }
+        }
+        bail!("TikTok publish {publish_id} did not finish after {MAX_STATUS_POLLS} polls")
+    }
+}
+
+#[async_trait]
+impl Publisher for TikTokPublisher {
+    fn platform(&self) -> &'static str {
+        "tiktok"
+    }
+
+    async fn publish(&self, request: &PostRequest) -> Result<PostResult> {
+        let privacy_level = privacy_level(request.metadata.privacy)?;
+        let bytes = read_video(&request.video_path).await?;
+        if bytes.len() > MAX_SINGLE_CHUNK_BYTES {
+            bail!(
+                "Video is {} bytes, TikTok single-chunk uploads max out at {MAX_SINGLE_CHUNK_BYTES}",
+                bytes.len()
+            );
+        }
+
+        let init = self.init(request, privacy_level, bytes.len()).await?;
+        self.upload(&init.upload_url, bytes).await?;
+        let public_id = self.wait_for_publish(&init.publish_id).await?;
+
+        Ok(PostResult {
+            platform: self.platform().to_string(),
+            url: public_id
+                .as_ref()
+                .map(|id| format!("https://www.tiktok.com/video/{id}")),
+            post_id: public_id.unwrap_or(init.publish_id),
+        })
+    }
+}
+
+/// TikTok's `privacy_level` for `privacy`. TikTok has no unlisted posts, and
+/// friends-only would still show the clip to people, so that is refused.
+fn privacy_level(privacy: Privacy) -> Result<&'static str> {
+    match privacy {
+        Privacy::Public => Ok("PUBLIC_TO_EVERYONE"),
+        Privacy::Private => Ok("SELF_ONLY"),
+        Privacy::Unlisted => bail!("TikTok has no unlisted posts, post it public or private"),
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use twitter_scraper::TwitterSession;
+
+use crate::{check_video, PostRequest, PostResult, Publisher};
+
+/// What `TwitterPublisher` needs from a logged-in X session, so tests can
+/// post through a fake one.
+#[async_trait]
+pub trait TweetSession: Send {
+    /// Post `caption` with the video at `video_path`, returning the tweet id.
+    async fn post_video(&self, caption: &str, video_path: &str) -> Result<String>;
+
+    /// Log in again if the session expired, returning whether it had.
+    async fn ensure_logged_in(&mut self) -> Result<bool>;
+}
+
+#[async_trait]
+impl TweetSession for TwitterSession {
+    async fn post_video(&self, caption: &str, video_path: &str) -> Result<String> {
+        twitter_scraper::post_tweet_with_video(self.scraper(), caption, video_path).await
+    }
+
+    async fn ensure_logged_in(&mut self) -> Result<bool> {
+        TwitterSession::ensure_logged_in(self).await
+    }
+}
+
+/// Posts through a logged-in session, same as `twitter_scraper::post_tweet_with_video`.
+/// If a post fails because the session expired, it logs in again and retries once.
+pub struct TwitterPublisher<S = TwitterSession> {
+    session: Arc<Mutex<S>>,
+}
+
+impl<S: TweetSession> TwitterPublisher<S> {
+    /// Post through `session`, the same one the X feeds are read with, so the
+    /// bot logs in once and both sides see a re-login.
+    pub fn new(session: Arc<Mutex<S>>) -> Self {
+        TwitterPublisher { session }
+    }
+}
+
+#[async_trait]
+impl<S: TweetSession> Publisher for TwitterPublisher<S> {
+    fn platform(&self) -> &'static str {
+        "twitter"
+    }
+
+    async fn publish(&self, request: &PostRequest) -> Result<PostResult> {
+        check_video(&request.video_path).await?;
+        let mut session = self.session.lock().await;
+        let tweet_id = match session
+            .post_video(&request.caption, &request.video_path)
+            .await
+        {
+            Ok(tweet_id) => tweet_id,
+            Err(e) => {
+                if !session.ensure_logged_in().await? {
+                    return Err(e);
+                }
+                session
+                    .post_video(&request.caption, &request.video_path)
+                    .await?
+            }
+        };
+
+        Ok(PostResult {
+            platform: self.platform().to_string(),
+            url: Some(format!("https://x.com/i/status/{tweet_id}")),
+            post_id: tweet_id,
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use reqwest::{header::LOCATION, Client};
+use serde::{Deserialize, Serialize};
+
+use crate::{read_video, truncate, PostRequest, PostResult, Privacy, Publisher};
+
+const DEFAULT_BASE_URL: &str = "https://www.googleapis.com";
+// "People & Blogs", YouTube's default category for uploads.
+const DEFAULT_CATEGORY_ID: &str = "22";
+const MAX_TITLE_CHARS: usize = 100;
+
+// -----------------------------------------------------------------------------
+// Data models for the YouTube Data API v3 `videos.insert` call
+// -----------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct VideoResource<'a> {
+    snippet: Snippet<'a>,
+    status: Status,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Snippet<'a> {
+    title: String,
+    description: String,
+    tags: &'a [String],
+    category_id: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Status {
+    privacy_status: &'static str,
+    self_declared_made_for_kids: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedVideo {
+    id: String,
+}
+
+/// Uploads Shorts through the YouTube Data API resumable upload flow.
+/// Needs an OAuth access token with the `youtube.upload` scope.
+pub struct YouTubePublisher {
+    client: Client,
+    base_url: String,
+    access_token: String,
+    category_id: String,
+}
+
+impl YouTubePublisher {
+    pub fn new(access_token: impl Into<String>) -> Self {
+        YouTubePublisher {
+            client: Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            access_token: access_token.into(),
+            category_id: DEFAULT_CATEGORY_ID.to_string(),
+        }
+    }
+
+    /// Point the publisher at a different host, e.g. a local HTTP stand-in.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_category_id(mut self, category_id: impl Into<String>) -> Self {
+        self.category_id = category_id.into();
+        self
+    }
+
+    /// Step 1: Create the upload session, returning the session URL.
+    async fn start_upload(&self, request: &PostRequest, video_len: usize) -> Result<String> {
+        let title = request
+            .metadata
+            .title
+            .as_deref()
+            .unwrap_or(&request.caption);
+        let body = VideoResource {
+            snippet: Snippet {
+                title: truncate(title, MAX_TITLE_CHARS),
+                description: format!("{}\n\n#Shorts", request.caption),
+                tags: &request.metadata.tags,
+                category_id: &self.category_id,
+            },
+            status: Status {
+                privacy_status: match request.metadata.privacy {
+                    Privacy::Public => "public",
+                    Privacy::Unlisted => "unlisted",
+                    Privacy::Private => "private",
+                },
+                self_declared_made_for_kids: false,
+            },
+        };
+
+        let url = format!(
+            "{}/upload/youtube/v3/videos?uploadType=resumable&part=snippet,status",
+            self.base_url.trim_end_matches('/')
+        );
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .header("X-Upload-Content-Type", "video/mp4")
+            .header("X-Upload-Content-Length", video_len.to_string())
+            .json(&body)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            bail!("YouTube upload session request failed, status: {status}, raw: {text}");
+        }
+
+        let session_url = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| anyhow!("YouTube did not return an upload session URL"))?;
+        Ok(session_url.to_string())
+    }
+
+    /// Step 2: Send the bytes to the session URL, returning the new video id.
+    async fn upload_bytes(&self, session_url: &str, bytes: Vec<u8>) -> Result<String> {
+        let resp = self
+            .client
+            .put(session_url)
+            .bearer_auth(&self.access_token)
+            .header("Content-Type", "video/mp4")
+            .body(bytes)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            bail!("YouTube upload failed, status: {status}, raw: {text}");
+        }
+
+        let text = resp.text().await?;
+        let uploaded: UploadedVideo = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse YouTube upload response: {e}, raw: {text}"))?;
+        Ok(uploaded.id)
+    }
+}
+
+#[async_trait]
+impl Publisher for YouTubePublisher {
+    fn platform(&self) -> &'static str {
+        "youtube"
+    }
+
+    async fn publish(&self, request: &PostRequest) -> Result<PostResult> {
+        let bytes = read_video(&request.video_path).await?;
+        let session_url = self.start_upload(request, bytes.len()).await?;
+        let video_id = self.upload_bytes(&session_url, bytes).await?;
+
+        Ok(PostResult {
+            platform: self.platform().to_string(),
+            url: Some(format!("https://youtube.com/shorts/{video_id}")),
+            post_id: video_id,
+        })
+    }
+}
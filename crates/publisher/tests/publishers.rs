@@ -0,0 +1,547 @@
+//! Each publisher's upload flow against a local stand-in of its API.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use publisher::{
+    InstagramPublisher, PostMetadata, PostRequest, PostResult, Privacy, Publisher, TikTokPublisher,
+    TweetSession, TwitterPublisher, UnreadableVideo, YouTubePublisher,
+};
+use serde_json::json;
+use std::{
+    io::Write,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+use tempfile::TempPath;
+use tokio::sync::Mutex;
+use wiremock::{
+    matchers::{body_bytes, body_partial_json, header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const VIDEO: &[u8] = b"not really an mp4";
+
+/// A post of a small temporary video, deleted with the post.
+struct TestPost {
+    request: PostRequest,
+    _video: TempPath,
+}
+
+impl Deref for TestPost {
+    type Target = PostRequest;
+
+    fn deref(&self) -> &PostRequest {
+        &self.request
+    }
+}
+
+impl DerefMut for TestPost {
+    fn deref_mut(&mut self) -> &mut PostRequest {
+        &mut self.request
+    }
+}
+
+/// A post for the test `name`.
+fn request(name: &str, privacy: Privacy) -> TestPost {
+    let mut video = tempfile::Builder::new()
+        .prefix(&format!("publisher-{name}-"))
+        .suffix(".mp4")
+        .tempfile()
+        .unwrap();
+    video.write_all(VIDEO).unwrap();
+    let video = video.into_temp_path();
+    TestPost {
+        request: PostRequest {
+            video_path: video.display().to_string(),
+            caption: "the cat learned rust".to_string(),
+            metadata: PostMetadata {
+                title: Some("Cat learns Rust".to_string()),
+                tags: vec!["rust".to_string()],
+                privacy,
+            },
+        },
+        _video: video,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// YouTube: resumable upload session, then the bytes
+// -----------------------------------------------------------------------------
+
+#[tokio::test]
+async fn youtube_uploads_through_a_resumable_session() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/upload/youtube/v3/videos"))
+        .and(query_param("uploadType", "resumable"))
+        .and(header("authorization", "Bearer yt-token"))
+        .and(header("x-upload-content-length", VIDEO.len().to_string()))
+        .and(body_partial_json(json!({
+            "snippet": { "title": "Cat learns Rust", "tags": ["rust"] },
+            "status": { "privacyStatus": "unlisted" },
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Location", format!("{}/session/1", server.uri())),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/session/1"))
+        .and(body_bytes(VIDEO))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "vid1" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = YouTubePublisher::new("yt-token")
+        .with_base_url(server.uri())
+        .publish(&request("youtube", Privacy::Unlisted))
+        .await
+        .unwrap();
+
+    assert_eq!(result.post_id, "vid1");
+    assert_eq!(
+        result.url.as_deref(),
+        Some("https://youtube.com/shorts/vid1")
+    );
+}
+
+#[tokio::test]
+async fn a_missing_video_is_unreadable_before_any_upload() {
+    let server = MockServer::start().await;
+    let mut request = request("youtube-missing", Privacy::Public);
+    request.video_path.push_str(".gone");
+
+    let error = YouTubePublisher::new("yt-token")
+        .with_base_url(server.uri())
+        .publish(&request)
+        .await
+        .unwrap_err();
+
+    assert!(error.downcast_ref::<UnreadableVideo>().is_some(), "{error}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn youtube_reports_a_refused_session() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/upload/youtube/v3/videos"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("bad token"))
+        .mount(&server)
+        .await;
+
+    let error = YouTubePublisher::new("yt-token")
+        .with_base_url(server.uri())
+        .publish(&request("youtube-401", Privacy::Public))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("401"), "{error}");
+    assert!(error.contains("bad token"), "{error}");
+}
+
+#[tokio::test]
+async fn youtube_reports_a_failed_upload() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/upload/youtube/v3/videos"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Location", format!("{}/session/1", server.uri())),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/session/1"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("backend error"))
+        .mount(&server)
+        .await;
+
+    let error = YouTubePublisher::new("yt-token")
+        .with_base_url(server.uri())
+        .publish(&request("youtube-500", Privacy::Public))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("upload failed"), "{error}");
+    assert!(error.contains("500"), "{error}");
+}
+
+// -----------------------------------------------------------------------------
+// TikTok: init, upload, then poll the publish status
+// -----------------------------------------------------------------------------
+
+fn tiktok(server: &MockServer) -> TikTokPublisher {
+    TikTokPublisher::new("tt-token")
+        .with_base_url(server.uri())
+        .with_poll_interval(Duration::from_millis(1))
+}
+
+async fn mount_tiktok_upload(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/v2/post/publish/video/init/"))
+        .and(header("authorization", "Bearer tt-token"))
+        .and(body_partial_json(json!({
+            "post_info": { "privacy_level": "SELF_ONLY" },
+            "source_info": { "source": "FILE_UPLOAD", "video_size": VIDEO.len() },
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "publish_id": "pub1", "upload_url": format!("{}/upload/pub1", server.uri()) },
+            "error": { "code": "ok" },
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/upload/pub1"))
+        .and(header(
+            "content-range",
+            format!("bytes 0-{}/{}", VIDEO.len() - 1, VIDEO.len()),
+        ))
+        .and(body_bytes(VIDEO))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+fn tiktok_status(data: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "data": data, "error": { "code": "ok" } }))
+}
+
+#[tokio::test]
+async fn tiktok_polls_until_the_post_is_public() {
+    let server = MockServer::start().await;
+    mount_tiktok_upload(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/v2/post/publish/status/fetch/"))
+        .and(body_partial_json(json!({ "publish_id": "pub1" })))
+        .respond_with(tiktok_status(json!({ "status": "PROCESSING_UPLOAD" })))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v2/post/publish/status/fetch/"))
+        .respond_with(tiktok_status(json!({
+            "status": "PUBLISH_COMPLETE",
+            "publicaly_available_post_id": [7300000000000000001u64],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = tiktok(&server)
+        .publish(&request("tiktok", Privacy::Private))
+        .await
+        .unwrap();
+
+    assert_eq!(result.post_id, "7300000000000000001");
+    assert_eq!(
+        result.url.as_deref(),
+        Some("https://www.tiktok.com/video/7300000000000000001")
+    );
+}
+
+#[tokio::test]
+async fn tiktok_reports_a_failed_publish() {
+    let server = MockServer::start().await;
+    mount_tiktok_upload(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/v2/post/publish/status/fetch/"))
+        .respond_with(tiktok_status(json!({
+            "status": "FAILED",
+            "fail_reason": "file_format_check_failed",
+        })))
+        .mount(&server)
+        .await;
+
+    let error = tiktok(&server)
+        .publish(&request("tiktok-failed", Privacy::Private))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("pub1 failed"), "{error}");
+    assert!(error.contains("file_format_check_failed"), "{error}");
+}
+
+#[tokio::test]
+async fn tiktok_reports_an_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v2/post/publish/video/init/"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "code": "access_token_invalid", "message": "The access token is invalid" },
+        })))
+        .mount(&server)
+        .await;
+
+    let error = tiktok(&server)
+        .publish(&request("tiktok-401", Privacy::Public))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("401"), "{error}");
+    assert!(error.contains("access_token_invalid"), "{error}");
+}
+
+#[tokio::test]
+async fn tiktok_refuses_unlisted_posts() {
+    let server = MockServer::start().await;
+
+    let error = tiktok(&server)
+        .publish(&request("tiktok-unlisted", Privacy::Unlisted))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("no unlisted posts"), "{error}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+// -----------------------------------------------------------------------------
+// Instagram: container, upload, poll, publish
+// -----------------------------------------------------------------------------
+
+fn instagram(server: &MockServer) -> InstagramPublisher {
+    InstagramPublisher::new("ig-token", "ig-user")
+        .with_base_url(server.uri())
+        .with_poll_interval(Duration::from_millis(1))
+}
+
+async fn mount_instagram_container(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/ig-user/media"))
+        .and(query_param("media_type", "REELS"))
+        .and(query_param("upload_type", "resumable"))
+        .and(query_param("caption", "the cat learned rust"))
+        .and(query_param("access_token", "ig-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "container1",
+            "uri": format!("{}/rupload/container1", server.uri()),
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/rupload/container1"))
+        .and(header("authorization", "OAuth ig-token"))
+        .and(header("file_size", VIDEO.len().to_string()))
+        .and(body_bytes(VIDEO))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn instagram_publishes_the_processed_container() {
+    let server = MockServer::start().await;
+    mount_instagram_container(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/container1"))
+        .and(query_param("fields", "status_code,status"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "status_code": "IN_PROGRESS" })),
+        )
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/container1"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "status_code": "FINISHED" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/ig-user/media_publish"))
+        .and(query_param("creation_id", "container1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "media1" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/media1"))
+        .and(query_param("fields", "permalink"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "permalink": "https://www.instagram.com/reel/abc/",
+        })))
+        .mount(&server)
+        .await;
+
+    let result = instagram(&server)
+        .publish(&request("instagram", Privacy::Public))
+        .await
+        .unwrap();
+
+    assert_eq!(result.post_id, "media1");
+    assert_eq!(
+        result.url.as_deref(),
+        Some("https://www.instagram.com/reel/abc/")
+    );
+}
+
+#[tokio::test]
+async fn instagram_reports_a_refused_container() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/ig-user/media"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": { "message": "Invalid OAuth access token", "code": 190 },
+        })))
+        .mount(&server)
+        .await;
+
+    let error = instagram(&server)
+        .publish(&request("instagram-400", Privacy::Public))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("create container failed"), "{error}");
+    assert!(error.contains("400"), "{error}");
+    assert!(error.contains("Invalid OAuth access token"), "{error}");
+}
+
+#[tokio::test]
+async fn instagram_reports_a_container_that_failed_processing() {
+    let server = MockServer::start().await;
+    mount_instagram_container(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/container1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status_code": "ERROR",
+            "status": "Error: Media upload has failed with error code 2207026",
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/ig-user/media_publish"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "media1" })))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let error = instagram(&server)
+        .publish(&request("instagram-error", Privacy::Public))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("container1 failed"), "{error}");
+    assert!(error.contains("2207026"), "{error}");
+}
+
+#[tokio::test]
+async fn instagram_refuses_posts_that_arent_public() {
+    let server = MockServer::start().await;
+
+    for privacy in [Privacy::Unlisted, Privacy::Private] {
+        let error = instagram(&server)
+            .publish(&request("instagram-hidden", privacy))
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("always public"), "{error}");
+    }
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+// -----------------------------------------------------------------------------
+// Twitter: post through the session, log in again once if it expired
+// -----------------------------------------------------------------------------
+
+/// A session that takes posts unless it `expired` (until the next login) or
+/// is `refusing` them outright.
+#[derive(Default)]
+struct FakeSession {
+    expired: bool,
+    refusing: bool,
+    logins: usize,
+    posts: StdMutex<Vec<(String, Vec<u8>)>>,
+}
+
+#[async_trait]
+impl TweetSession for FakeSession {
+    async fn post_video(&self, caption: &str, video_path: &str) -> Result<String> {
+        if self.expired {
+            bail!("Could not authenticate you");
+        }
+        if self.refusing {
+            bail!("Status is a duplicate");
+        }
+        let mut posts = self.posts.lock().unwrap();
+        posts.push((caption.to_string(), std::fs::read(video_path)?));
+        Ok(format!("tweet{}", posts.len()))
+    }
+
+    async fn ensure_logged_in(&mut self) -> Result<bool> {
+        let expired = std::mem::take(&mut self.expired);
+        if expired {
+            self.logins += 1;
+        }
+        Ok(expired)
+    }
+}
+
+async fn tweet(session: FakeSession, name: &str) -> (Result<PostResult>, FakeSession) {
+    let session = Arc::new(Mutex::new(session));
+    let result = TwitterPublisher::new(session.clone())
+        .publish(&request(name, Privacy::Public))
+        .await;
+    let session = Arc::into_inner(session).unwrap().into_inner();
+    (result, session)
+}
+
+#[tokio::test]
+async fn twitter_posts_the_caption_and_video() {
+    let (result, session) = tweet(FakeSession::default(), "twitter").await;
+
+    let result = result.unwrap();
+    assert_eq!(result.post_id, "tweet1");
+    assert_eq!(result.url.as_deref(), Some("https://x.com/i/status/tweet1"));
+    assert_eq!(
+        *session.posts.lock().unwrap(),
+        [("the cat learned rust".to_string(), VIDEO.to_vec())]
+    );
+    assert_eq!(session.logins, 0);
+}
+
+#[tokio::test]
+async fn twitter_logs_in_again_when_the_session_expired() {
+    let expired = FakeSession {
+        expired: true,
+        ..FakeSession::default()
+    };
+    let (result, session) = tweet(expired, "twitter-expired").await;
+
+    assert_eq!(result.unwrap().post_id, "tweet1");
+    assert_eq!(session.logins, 1);
+    assert_eq!(session.posts.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn twitter_reports_a_refused_post_without_retrying() {
+    let refusing = FakeSession {
+        refusing: true,
+        ..FakeSession::default()
+    };
+    let (result, session) = tweet(refusing, "twitter-refused").await;
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("duplicate"), "{error}");
+    assert_eq!(session.logins, 0);
+    assert!(session.posts.lock().unwrap().is_empty());
+}
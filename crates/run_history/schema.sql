@@ -0,0 +1,73 @@
+-- Run history: one row per tweet cycle, plus what went into it and what came out.
+-- Applied on every open, so everything here must be idempotent.
+
+-- One tweet cycle. run_id is the unix timestamp the cycle started at, the same
+-- number as its directory under DATA_ROOT.
+CREATE TABLE IF NOT EXISTS runs (
+    run_id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    finished_at TEXT,
+    status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'skipped', 'failed')),
+    -- Why a run was skipped or failed
+    status_detail TEXT,
+    section TEXT,
+    -- The feed as shown in logs, e.g. "@handle" or "r/rust"
+    source TEXT,
+    source_key TEXT,
+    prompt_version TEXT,
+    persona TEXT,
+    topic TEXT,
+    tweet_text TEXT,
+    run_dir TEXT NOT NULL,
+    video_path TEXT,
+    cost_usd REAL NOT NULL DEFAULT 0
+);
+
+-- The new tweets a run summarized, and which of them the topic came from.
+CREATE TABLE IF NOT EXISTS run_tweets (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+    tweet_id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    text TEXT NOT NULL,
+    url TEXT,
+    used_for_topic INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (run_id, tweet_id)
+);
+
+-- How long each stage took and whether it worked.
+CREATE TABLE IF NOT EXISTS run_stages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+    stage TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('ok', 'failed')),
+    error TEXT
+);
+
+-- What a run spent, one row per provider call or batch of calls.
+CREATE TABLE IF NOT EXISTS run_costs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    item TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit TEXT NOT NULL,
+    cost_usd REAL NOT NULL DEFAULT 0,
+    recorded_at TEXT NOT NULL
+);
+
+-- Where the video ended up.
+CREATE TABLE IF NOT EXISTS run_posts (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+    platform TEXT NOT NULL,
+    post_id TEXT NOT NULL,
+    url TEXT,
+    posted_at TEXT NOT NULL,
+    PRIMARY KEY (run_id, platform)
+);
+
+CREATE INDEX IF NOT EXISTS idx_runs_status ON runs(status);
+CREATE INDEX IF NOT EXISTS idx_runs_source_key ON runs(source_key);
+CREATE INDEX IF NOT EXISTS idx_run_stages_run_id ON run_stages(run_id);
+CREATE INDEX IF NOT EXISTS idx_run_costs_run_id ON run_costs(run_id);
@@ -0,0 +1,511 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+const SCHEMA: &str = include_str!("../schema.sql");
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    Completed,
+    /// Nothing new to summarize, no topic, or the safety gate said no.
+    Skipped,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Completed => "completed",
+            RunStatus::Skipped => "skipped",
+            RunStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "running" => Ok(RunStatus::Running),
+            "completed" => Ok(RunStatus::Completed),
+            "skipped" => Ok(RunStatus::Skipped),
+            "failed" => Ok(RunStatus::Failed),
+            other => bail!(
+                "Unknown run status '{other}', expected running, completed, skipped or failed"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where a run got its material from and how it was prompted.
+#[derive(Debug, Clone)]
+pub struct RunSource {
+    pub section: String,
+    /// The feed as shown in logs, e.g. "@handle" or "r/rust".
+    pub source: String,
+    pub source_key: String,
+    pub prompt_version: String,
+    pub persona: String,
+}
+
+/// A tweet (or post) a run summarized.
+#[derive(Debug, Clone)]
+pub struct RunTweet {
+    pub tweet_id: String,
+    pub author: String,
+    pub text: String,
+    pub url: Option<String>,
+    /// The summarizer named it as a source of the topic.
+    pub used_for_topic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StageRecord {
+    pub stage: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+/// Usage of one provider, e.g. 1200 "tokens" of "gpt-4o" from "openai".
+#[derive(Debug, Clone)]
+pub struct CostRecord {
+    pub provider: String,
+    pub item: String,
+    pub quantity: f64,
+    pub unit: String,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PostRecord {
+    pub platform: String,
+    pub post_id: String,
+    pub url: Option<String>,
+    pub posted_at: DateTime<Utc>,
+}
+
+/// One row of the `runs` table.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub run_id: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: RunStatus,
+    pub status_detail: Option<String>,
+    pub section: Option<String>,
+    pub source: Option<String>,
+    pub source_key: Option<String>,
+    pub prompt_version: Option<String>,
+    pub persona: Option<String>,
+    pub topic: Option<String>,
+    pub tweet_text: Option<String>,
+    pub run_dir: String,
+    pub video_path: Option<String>,
+    pub cost_usd: f64,
+}
+
+/// A run with everything recorded against it.
+#[derive(Debug, Clone)]
+pub struct RunDetail {
+    pub run: Run,
+    pub tweets: Vec<RunTweet>,
+    pub stages: Vec<StageRecord>,
+    pub costs: Vec<CostRecord>,
+    pub posts: Vec<PostRecord>,
+}
+
+// -----------------------------------------------------------------------------
+// Store
+// -----------------------------------------------------------------------------
+
+/// SQLite database of every run: source, tweets, topic, tweet text, prompt
+/// version, costs, stage timings, output paths, post ids and status.
+///
+/// The run directories under the data root get pruned, this history doesn't.
+pub struct RunHistory {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl RunHistory {
+    /// Open (or create) the database and bring the schema up to date.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let conn = Connection::open(&path)
+            .map_err(|e| anyhow!("Failed to open run history {}: {e}", path.display()))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| anyhow!("Failed to apply run history schema: {e}"))?;
+        Ok(RunHistory {
+            path,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("Run history lock poisoned"))
+    }
+
+    /// Fails if `run_id` is already in the history, rather than replacing
+    /// that run and everything recorded about it.
+    pub fn start_run(&self, run_id: i64, run_dir: &Path) -> Result<()> {
+        let inserted = self.conn()?.execute(
+            "INSERT INTO runs (run_id, started_at, status, run_dir)
+             VALUES (?1, ?2, 'running', ?3)",
+            params![run_id, Utc::now(), run_dir.to_string_lossy()],
+        );
+        match inserted {
+            Ok(_) => Ok(()),
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                bail!(
+                    "Run {run_id} is already in the run history {}",
+                    self.path.display()
+                )
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_source(&self, run_id: i64, source: &RunSource) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE runs SET section = ?2, source = ?3, source_key = ?4,
+                 prompt_version = ?5, persona = ?6
+             WHERE run_id = ?1",
+            params![
+                run_id,
+                source.section,
+                source.source,
+                source.source_key,
+                source.prompt_version,
+                source.persona
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_tweets(&self, run_id: i64, tweets: &[RunTweet]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        for tweet in tweets {
+            tx.execute(
+                "INSERT OR REPLACE INTO run_tweets (run_id, tweet_id, author, text, url, used_for_topic)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    run_id,
+                    tweet.tweet_id,
+                    tweet.author,
+                    tweet.text,
+                    tweet.url,
+                    tweet.used_for_topic
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn set_topic(&self, run_id: i64, topic: &str) -> Result<()> {
+        self.set(run_id, "topic", topic)
+    }
+
+    pub fn set_tweet_text(&self, run_id: i64, tweet_text: &str) -> Result<()> {
+        self.set(run_id, "tweet_text", tweet_text)
+    }
+
+    pub fn set_video_path(&self, run_id: i64, video_path: &str) -> Result<()> {
+        self.set(run_id, "video_path", video_path)
+    }
+
+    // `column` is always one of ours, never user input
+    fn set(&self, run_id: i64, column: &'static str, value: &str) -> Result<()> {
+        self.conn()?.execute(
+            &format!("UPDATE runs SET {column} = ?2 WHERE run_id = ?1"),
+            params![run_id, value],
+        )?;
+        Ok(())
+    }
+
+    /// A stage finished after `duration`, with `error` if it failed.
+    pub fn record_stage(
+        &self,
+        run_id: i64,
+        stage: &str,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let status = if error.is_some() { "failed" } else { "ok" };
+        self.conn()?.execute(
+            "INSERT INTO run_stages (run_id, stage, started_at, duration_ms, status, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                run_id,
+                stage,
+                started_at,
+                duration.as_millis() as i64,
+                status,
+                error
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Add usage to the run and its cost to the run's total.
+    pub fn record_cost(&self, run_id: i64, cost: &CostRecord) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO run_costs (run_id, provider, item, quantity, unit, cost_usd, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                run_id,
+                cost.provider,
+                cost.item,
+                cost.quantity,
+                cost.unit,
+                cost.cost_usd,
+                Utc::now()
+            ],
+        )?;
+        tx.execute(
+            "UPDATE runs SET cost_usd = cost_usd + ?2 WHERE run_id = ?1",
+            params![run_id, cost.cost_usd],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn record_post(
+        &self,
+        run_id: i64,
+        platform: &str,
+        post_id: &str,
+        url: Option<&str>,
+    ) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT OR REPLACE INTO run_posts (run_id, platform, post_id, url, posted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run_id, platform, post_id, url, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// What every run has cost since `since`, for the daily budget.
+    pub fn spent_since(&self, since: DateTime<Utc>) -> Result<f64> {
+        let spent = self.conn()?.query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM run_costs WHERE recorded_at >= ?1",
+            params![since],
+            |row| row.get(0),
+        )?;
+        Ok(spent)
+    }
+
+    /// How many posts went to `platform` since `since`, for its daily limit.
+    pub fn posts_since(&self, platform: &str, since: DateTime<Utc>) -> Result<u32> {
+        let posts = self.conn()?.query_row(
+            "SELECT COUNT(*) FROM run_posts WHERE platform = ?1 AND posted_at >= ?2",
+            params![platform, since],
+            |row| row.get(0),
+        )?;
+        Ok(posts)
+    }
+
+    /// When anything was last posted, for the spacing between posts.
+    pub fn last_post_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let posted_at =
+            self.conn()?
+                .query_row("SELECT MAX(posted_at) FROM run_posts", [], |row| row.get(0))?;
+        Ok(posted_at)
+    }
+
+    /// `detail` is why a run was skipped or failed.
+    pub fn finish_run(&self, run_id: i64, status: RunStatus, detail: Option<&str>) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE runs SET status = ?2, status_detail = ?3, finished_at = ?4 WHERE run_id = ?1",
+            params![run_id, status.as_str(), detail, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+    // Queries
+    // -------------------------------------------------------------------------
+
+    /// The latest `limit` runs, newest first, optionally only those with `status`.
+    pub fn runs(&self, limit: usize, status: Option<RunStatus>) -> Result<Vec<Run>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT run_id, started_at, finished_at, status, status_detail, section, source,
+                    source_key, prompt_version, persona, topic, tweet_text, run_dir,
+                    video_path, cost_usd
+             FROM runs
+             WHERE ?1 IS NULL OR status = ?1
+             ORDER BY run_id DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(
+            params![status.map(|s| s.as_str()), limit as i64],
+            run_from_row,
+        )?;
+        rows.map(|row| row?).collect()
+    }
+
+    /// Everything recorded about `run_id`, `None` if there is no such run.
+    pub fn run(&self, run_id: i64) -> Result<Option<RunDetail>> {
+        let conn = self.conn()?;
+        let Some(run) = conn
+            .query_row(
+                "SELECT run_id, started_at, finished_at, status, status_detail, section, source,
+                        source_key, prompt_version, persona, topic, tweet_text, run_dir,
+                        video_path, cost_usd
+                 FROM runs WHERE run_id = ?1",
+                params![run_id],
+                run_from_row,
+            )
+            .optional()?
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+
+        let tweets = conn
+            .prepare(
+                "SELECT tweet_id, author, text, url, used_for_topic
+                 FROM run_tweets WHERE run_id = ?1 ORDER BY used_for_topic DESC, tweet_id",
+            )?
+            .query_map(params![run_id], |row| {
+                Ok(RunTweet {
+                    tweet_id: row.get(0)?,
+                    author: row.get(1)?,
+                    text: row.get(2)?,
+                    url: row.get(3)?,
+                    used_for_topic: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let stages = conn
+            .prepare(
+                "SELECT stage, started_at, duration_ms, error
+                 FROM run_stages WHERE run_id = ?1 ORDER BY id",
+            )?
+            .query_map(params![run_id], |row| {
+                Ok(StageRecord {
+                    stage: row.get(0)?,
+                    started_at: row.get(1)?,
+                    duration_ms: row.get(2)?,
+                    error: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let costs = conn
+            .prepare(
+                "SELECT provider, item, quantity, unit, cost_usd
+                 FROM run_costs WHERE run_id = ?1 ORDER BY id",
+            )?
+            .query_map(params![run_id], |row| {
+                Ok(CostRecord {
+                    provider: row.get(0)?,
+                    item: row.get(1)?,
+                    quantity: row.get(2)?,
+                    unit: row.get(3)?,
+                    cost_usd: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let posts = conn
+            .prepare(
+                "SELECT platform, post_id, url, posted_at
+                 FROM run_posts WHERE run_id = ?1 ORDER BY posted_at",
+            )?
+            .query_map(params![run_id], |row| {
+                Ok(PostRecord {
+                    platform: row.get(0)?,
+                    post_id: row.get(1)?,
+                    url: row.get(2)?,
+                    posted_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(Some(RunDetail {
+            run,
+            tweets,
+            stages,
+            costs,
+            posts,
+        }))
+    }
+}
+
+// The status column is checked by the schema, but parse it properly anyway
+fn run_from_row(row: &Row<'_>) -> rusqlite::Result<Result<Run>> {
+    let status: String = row.get(3)?;
+    let status = match RunStatus::parse(&status) {
+        Ok(status) => status,
+        Err(e) => return Ok(Err(e)),
+    };
+    Ok(Ok(Run {
+        run_id: row.get(0)?,
+        started_at: row.get(1)?,
+        finished_at: row.get(2)?,
+        status,
+        status_detail: row.get(4)?,
+        section: row.get(5)?,
+        source: row.get(6)?,
+        source_key: row.get(7)?,
+        prompt_version: row.get(8)?,
+        persona: row.get(9)?,
+        topic: row.get(10)?,
+        tweet_text: row.get(11)?,
+        run_dir: row.get(12)?,
+        video_path: row.get(13)?,
+        cost_usd: row.get(14)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_run_keeps_an_existing_run() {
+        let history = RunHistory::open(":memory:").unwrap();
+        let dir = Path::new("tmp/1");
+        history.start_run(1, dir).unwrap();
+        history
+            .record_stage(1, "source", Utc::now(), Duration::from_millis(5), None)
+            .unwrap();
+
+        let error = history.start_run(1, dir).unwrap_err();
+        assert!(error.to_string().contains("already in the run history"));
+        let detail = history.run(1).unwrap().unwrap();
+        assert_eq!(detail.stages.len(), 1);
+    }
+}
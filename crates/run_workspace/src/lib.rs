@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub mod storage;
+
+pub use storage::{GcReport, Retention, Storage};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+/// One file a stage produced, as listed in `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Relative to the run directory.
+    pub path: String,
+    /// The stage that wrote it, e.g. "audio" or "render".
+    pub stage: String,
+    pub sha256: String,
+    pub bytes: u64,
+    /// What it was made from: prompts, models, voices, seeds, other artifacts.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Everything a run produced. Re-running a stage replaces its entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub run_id: i64,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Manifest {
+    pub fn artifact(&self, path: &str) -> Option<&Artifact> {
+        self.artifacts.iter().find(|a| a.path == path)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Run directory layout
+// -----------------------------------------------------------------------------
+
+/// The directory of one run and the names of everything in it:
+///
+/// ```text
+/// <root>/<run_id>/                  root is the data root, see `Storage`
+///   manifest.json       every artifact below, with checksums and inputs
+///   topic.json, prompts.json, critic_scores.json, safety.jsonl
+///   tts.mp3             ElevenLabs audio
+///   tts.srt             whisperx transcription, word highlights
+///   final_srt.srt       one highlighted word per entry
+///   tts.ass             final_srt.srt converted for burning in
+///   word_<line>_<n>.png Fal images
+///   filelist.txt        ffmpeg concat list of the images
+///   <timestamp>.mp4     slideshow with audio
+///   final_output.mp4    slideshow with subtitles burnt in
+/// ```
+///
+/// Stages ask the workspace for paths instead of formatting them, so the layout
+/// lives in one place.
+#[derive(Debug, Clone)]
+pub struct RunWorkspace {
+    run_id: i64,
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl RunWorkspace {
+    /// Create (or reuse) the directory for `run_id` under `root`.
+    pub fn create(root: impl Into<PathBuf>, run_id: i64) -> Result<Self> {
+        let workspace = RunWorkspace::open(root, run_id);
+        fs::create_dir_all(&workspace.dir)
+            .map_err(|e| anyhow!("Failed to create {}: {e}", workspace.dir.display()))?;
+        Ok(workspace)
+    }
+
+    /// The workspace for `run_id` under `root`, without touching the disk.
+    pub fn open(root: impl Into<PathBuf>, run_id: i64) -> Self {
+        let root = root.into();
+        RunWorkspace {
+            run_id,
+            dir: root.join(run_id.to_string()),
+            root,
+        }
+    }
+
+    pub fn run_id(&self) -> i64 {
+        self.run_id
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Any file in the run directory, for the small JSON reports stages write.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn tts_audio(&self) -> PathBuf {
+        self.file("tts.mp3")
+    }
+
+    /// whisperx names its output after the audio file.
+    pub fn transcription_srt(&self) -> PathBuf {
+        self.file("tts.srt")
+    }
+
+    pub fn final_srt(&self) -> PathBuf {
+        self.file("final_srt.srt")
+    }
+
+    pub fn subtitles_ass(&self) -> PathBuf {
+        self.file("tts.ass")
+    }
+
+    /// Bare file name, `filelist.txt` refers to images relative to itself.
+    pub fn image_name(line: usize, index: usize) -> String {
+        format!("word_{line}_{index}.png")
+    }
+
+    pub fn image(&self, line: usize, index: usize) -> PathBuf {
+        self.file(&RunWorkspace::image_name(line, index))
+    }
+
+    pub fn filelist(&self) -> PathBuf {
+        self.file("filelist.txt")
+    }
+
+    pub fn slideshow(&self, timestamp: i64) -> PathBuf {
+        self.file(&format!("{timestamp}.mp4"))
+    }
+
+    pub fn final_video(&self) -> PathBuf {
+        self.file("final_output.mp4")
+    }
+
+    /// Whether a file survives pruning: the final video, the manifest and the
+    /// JSON reports. Everything else can be made again from those.
+    pub fn is_final(name: &str) -> bool {
+        name == "final_output.mp4" || name.ends_with(".json") || name.ends_with(".jsonl")
+    }
+
+    /// The files in the run directory that pruning deletes.
+    pub fn intermediates(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = entry.file_name();
+            if !RunWorkspace::is_final(&name.to_string_lossy()) {
+                files.push(entry.path());
+            }
+        }
+        Ok(files)
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.file(MANIFEST_FILE)
+    }
+
+    /// The manifest so far, or an empty one for a fresh run.
+    pub fn manifest(&self) -> Result<Manifest> {
+        let path = self.manifest_path();
+        if !path.exists() {
+            return Ok(Manifest {
+                run_id: self.run_id,
+                created_at: Utc::now(),
+                artifacts: Vec::new(),
+            });
+        }
+        let raw = fs::read_to_string(&path)?;
+        serde_json::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))
+    }
+
+    /// Checksum `path` and list it in `manifest.json` under `stage`.
+    ///
+    /// `inputs` is whatever made the file reproducible, e.g.
+    /// `[("model", "eleven_turbo_v2"), ("voice", "Ethan")]`.
+    pub fn record(
+        &self,
+        path: impl AsRef<Path>,
+        stage: &str,
+        inputs: &[(&str, &str)],
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let content =
+            fs::read(path).map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+        let relative = path
+            .strip_prefix(&self.dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        let artifact = Artifact {
+            path: relative,
+            stage: stage.to_string(),
+            sha256: format!("{:x}", Sha256::digest(&content)),
+            bytes: content.len() as u64,
+            inputs: inputs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            created_at: Utc::now(),
+        };
+
+        let mut manifest = self.manifest()?;
+        manifest.artifacts.retain(|a| a.path != artifact.path);
+        manifest.artifacts.push(artifact);
+        self.save_manifest(&manifest)
+    }
+
+    fn save_manifest(&self, manifest: &Manifest) -> Result<()> {
+        let path = self.manifest_path();
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(manifest)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
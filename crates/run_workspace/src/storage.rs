@@ -0,0 +1,241 @@
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::RunWorkspace;
+
+// Runs this young may still be rendering, gc never touches them.
+const ACTIVE_RUN_GRACE_SECS: i64 = 60 * 60;
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// How long run directories are kept:
+///
+/// * `prune_after_days`: intermediates (audio, subtitles, images, the
+///   unsubtitled slideshow) are deleted after this many days (default 7).
+///   The final video and the JSON reports stay.
+/// * `max_total_bytes`: once the data root is bigger than this, whole runs
+///   are deleted oldest first until it fits. `None` means no cap.
+#[derive(Debug, Clone)]
+pub struct Retention {
+    pub prune_after_days: u64,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            prune_after_days: 7,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl Retention {
+    pub fn new(prune_after_days: u64, max_total_mb: Option<u64>) -> Self {
+        Retention {
+            prune_after_days,
+            max_total_bytes: max_total_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+}
+
+/// What a gc pass did, or would do with `dry_run`.
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    pub runs_scanned: usize,
+    pub files_pruned: usize,
+    pub runs_removed: usize,
+    pub bytes_freed: u64,
+    pub bytes_remaining: u64,
+}
+
+/// The data root every run directory lives under, and its retention policy.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    root: PathBuf,
+    retention: Retention,
+}
+
+impl Storage {
+    pub fn new(root: impl Into<PathBuf>, retention: Retention) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)
+            .map_err(|e| anyhow!("Failed to create data root {}: {e}", root.display()))?;
+        Ok(Storage { root, retention })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn retention(&self) -> &Retention {
+        &self.retention
+    }
+
+    /// Create the directory for a new run.
+    pub fn create_run(&self, run_id: i64) -> Result<RunWorkspace> {
+        RunWorkspace::create(&self.root, run_id)
+    }
+
+    /// Every run directory under the root, oldest first. Anything that isn't a
+    /// directory named after a run id is left alone.
+    pub fn runs(&self) -> Result<Vec<RunWorkspace>> {
+        let mut runs = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            // file_type doesn't follow symlinks, so a link to elsewhere is never a run
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(run_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            runs.push(RunWorkspace::open(&self.root, run_id));
+        }
+        runs.sort_by_key(|run| run.run_id());
+        Ok(runs)
+    }
+
+    /// Apply the retention policy. Run ids are unix timestamps, that's the age.
+    ///
+    /// 1. Runs older than `prune_after_days` lose their intermediates.
+    /// 2. If the root is still over `max_total_bytes`, whole runs go, oldest first.
+    ///
+    /// Runs started in the last hour are never touched, they may still be going.
+    /// Runs in `keep`, like the ones whose clip waits in the queue, are never removed.
+    pub fn gc(&self, now: i64, keep: &HashSet<i64>, dry_run: bool) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        let prune_before = now - self.retention.prune_after_days as i64 * SECS_PER_DAY;
+        let active_after = now - ACTIVE_RUN_GRACE_SECS;
+
+        let runs = self.runs()?;
+        report.runs_scanned = runs.len();
+        let mut sizes = Vec::new();
+
+        for run in &runs {
+            let mut pruned = 0;
+            if run.run_id() < prune_before && run.run_id() < active_after {
+                for path in run.intermediates()? {
+                    let bytes = fs::metadata(&path)?.len();
+                    if !dry_run {
+                        fs::remove_file(&path)?;
+                    }
+                    report.files_pruned += 1;
+                    pruned += bytes;
+                }
+            }
+            report.bytes_freed += pruned;
+            let size = dir_size(run.dir())?;
+            // A dry run deleted nothing, count what it would have
+            sizes.push(if dry_run {
+                size.saturating_sub(pruned)
+            } else {
+                size
+            });
+        }
+        let mut total: u64 = sizes.iter().sum();
+
+        if let Some(cap) = self.retention.max_total_bytes {
+            for (run, size) in runs.iter().zip(&sizes) {
+                if total <= cap {
+                    break;
+                }
+                if run.run_id() >= active_after || keep.contains(&run.run_id()) {
+                    continue;
+                }
+                remove_run(&self.root, run, dry_run)?;
+                report.runs_removed += 1;
+                report.bytes_freed += size;
+                total = total.saturating_sub(*size);
+            }
+        }
+
+        report.bytes_remaining = total;
+        Ok(report)
+    }
+}
+
+/// Delete a run directory, after checking it really is one under `root`.
+fn remove_run(root: &Path, run: &RunWorkspace, dry_run: bool) -> Result<()> {
+    let dir = run.dir();
+    if dir.parent() != Some(root) || fs::symlink_metadata(dir)?.file_type().is_symlink() {
+        bail!("Refusing to delete {}, not a run directory", dir.display());
+    }
+    if !dry_run {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = SECS_PER_DAY;
+
+    fn storage(name: &str, max_total_mb: Option<u64>) -> Storage {
+        let root = std::env::temp_dir().join(format!("storage-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        Storage::new(root, Retention::new(7, max_total_mb)).unwrap()
+    }
+
+    /// A run with a 1 MB final video.
+    fn finished_run(storage: &Storage, run_id: i64) {
+        let run = storage.create_run(run_id).unwrap();
+        fs::write(run.final_video(), vec![0u8; 1024 * 1024]).unwrap();
+    }
+
+    #[test]
+    fn the_cap_removes_old_runs_but_not_queued_ones() {
+        let storage = storage("cap", Some(2));
+        let now = 100 * DAY;
+        for run_id in [now - 4 * DAY, now - 3 * DAY, now - 2 * DAY, now - 1] {
+            finished_run(&storage, run_id);
+        }
+        // The oldest run's clip still waits for a slot
+        let queued = HashSet::from([now - 4 * DAY]);
+
+        let report = storage.gc(now, &queued, false).unwrap();
+
+        let left: Vec<i64> = storage.runs().unwrap().iter().map(|r| r.run_id()).collect();
+        assert_eq!(left, [now - 4 * DAY, now - 1]);
+        assert_eq!(report.runs_removed, 2);
+        fs::remove_dir_all(storage.root()).unwrap();
+    }
+
+    #[test]
+    fn a_dry_run_removes_nothing() {
+        let storage = storage("dry", Some(1));
+        let now = 100 * DAY;
+        for run_id in [now - 3 * DAY, now - 2 * DAY] {
+            finished_run(&storage, run_id);
+        }
+
+        let report = storage.gc(now, &HashSet::new(), true).unwrap();
+
+        assert_eq!(report.runs_removed, 1);
+        assert_eq!(storage.runs().unwrap().len(), 2);
+        fs::remove_dir_all(storage.root()).unwrap();
+    }
+}
@@ -0,0 +1,555 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub mod selector;
+
+pub use selector::{SelectorConfig, SourceSelector};
+
+/// A group of accounts that share a vibe, e.g. "crypto", "ai" or "funny".
+/// Besides its accounts a section can pull from whole-niche feeds:
+/// keyword searches, X lists, trending topics and the logged-in home timeline,
+/// plus RSS/Atom feeds, subreddits, the Hacker News front page and saved tweets,
+/// which don't need X at all.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Section {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Search queries, any X search operators work, e.g. `"rust lang" min_faves:100`.
+    #[serde(default)]
+    pub searches: Vec<String>,
+    /// Numeric X list ids.
+    #[serde(default)]
+    pub lists: Vec<String>,
+    #[serde(default)]
+    pub trends: bool,
+    #[serde(default)]
+    pub home_timeline: bool,
+    /// RSS or Atom feed URLs. A local file path works too.
+    #[serde(default)]
+    pub rss: Vec<String>,
+    /// Subreddit names without the `r/`.
+    #[serde(default)]
+    pub subreddits: Vec<String>,
+    #[serde(default)]
+    pub hacker_news: bool,
+    /// Saved tweets, JSON files of `SourceTweet`s, for running without X or the network.
+    #[serde(default)]
+    pub tweet_fixtures: Vec<String>,
+    /// Weight of each of the feeds above, compared against account weights.
+    #[serde(default = "default_weight")]
+    pub feed_weight: f64,
+}
+
+/// One account we pull tweets from.
+/// `id` is optional: accounts without one are resolved from `handle` at startup.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Account {
+    pub handle: String,
+    pub id: Option<String>,
+    pub section: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// The on-disk shape of the registry file (TOML or JSON).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SourceConfig {
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    #[serde(default)]
+    pub selector: SelectorConfig,
+}
+
+/// An enabled account whose numeric id is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAccount {
+    pub handle: String,
+    pub id: String,
+    pub section: String,
+    pub weight: f64,
+}
+
+/// Where a cycle's tweets come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Feed {
+    User { handle: String, id: String },
+    Search(String),
+    List(String),
+    Trends,
+    HomeTimeline,
+    Rss(String),
+    Subreddit(String),
+    HackerNews,
+    TweetFixture(String),
+}
+
+impl Feed {
+    /// Whether fetching this feed needs a logged-in X scraper.
+    pub fn needs_twitter(&self) -> bool {
+        !matches!(
+            self,
+            Feed::Rss(_) | Feed::Subreddit(_) | Feed::HackerNews | Feed::TweetFixture(_)
+        )
+    }
+}
+
+impl fmt::Display for Feed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feed::User { handle, .. } => write!(f, "@{handle}"),
+            Feed::Search(query) => write!(f, "search \"{query}\""),
+            Feed::List(id) => write!(f, "list {id}"),
+            Feed::Trends => write!(f, "trends"),
+            Feed::HomeTimeline => write!(f, "home timeline"),
+            Feed::Rss(url) => write!(f, "rss {url}"),
+            Feed::Subreddit(name) => write!(f, "r/{name}"),
+            Feed::HackerNews => write!(f, "hacker news"),
+            Feed::TweetFixture(path) => write!(f, "tweets from {path}"),
+        }
+    }
+}
+
+/// Something the selector can pick: an account or one of a section's feeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceCandidate {
+    /// Stable id used for cooldowns and freshness, e.g. `user:1605` or `ai/search:o3`.
+    pub key: String,
+    pub feed: Feed,
+    pub section: String,
+    pub weight: f64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl SourceConfig {
+    /// Parse a registry file, picking the format from the extension (`.json` or TOML).
+    pub fn parse(path: &Path, raw: &str) -> Result<Self> {
+        let config: SourceConfig = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(raw)
+                .map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))?,
+            _ => toml::from_str(raw)
+                .map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject configs that would silently never pick an account.
+    fn validate(&self) -> Result<()> {
+        let sections: HashSet<&str> = self.sections.iter().map(|s| s.name.as_str()).collect();
+        for section in &self.sections {
+            if !section.feed_weight.is_finite() || section.feed_weight < 0.0 {
+                bail!("Section '{}' has an invalid feed_weight", section.name);
+            }
+            if let Some(list) = section
+                .lists
+                .iter()
+                .find(|l| l.is_empty() || !l.chars().all(|c| c.is_ascii_digit()))
+            {
+                bail!(
+                    "Section '{}' has list '{list}', lists must be numeric ids",
+                    section.name
+                );
+            }
+            if section.searches.iter().any(|q| q.trim().is_empty()) {
+                bail!("Section '{}' has an empty search query", section.name);
+            }
+            if section
+                .subreddits
+                .iter()
+                .any(|r| r.is_empty() || r.starts_with("r/"))
+            {
+                bail!(
+                    "Section '{}' has a bad subreddit, use the bare name like \"rust\"",
+                    section.name
+                );
+            }
+        }
+        let mut handles = HashSet::new();
+
+        for account in &self.accounts {
+            if !sections.contains(account.section.as_str()) {
+                bail!(
+                    "Account '{}' uses unknown section '{}'",
+                    account.handle,
+                    account.section
+                );
+            }
+            if !account.weight.is_finite() || account.weight < 0.0 {
+                bail!("Account '{}' has an invalid weight", account.handle);
+            }
+            if !handles.insert(account.handle.to_lowercase()) {
+                bail!("Account '{}' is listed twice", account.handle);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sections and accounts loaded from a file, reloaded when the file changes.
+pub struct SourceRegistry {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    config: SourceConfig,
+    // handle (lowercase) -> id, kept across reloads so we only look handles up once
+    resolved_ids: HashMap<String, String>,
+    selector: SourceSelector,
+}
+
+impl SourceRegistry {
+    /// Load the registry from `path`.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let raw = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read source registry {}: {e}", path.display()))?;
+        let config = SourceConfig::parse(&path, &raw)?;
+
+        Ok(SourceRegistry {
+            modified: modified_time(&path),
+            path,
+            selector: SourceSelector::new(config.selector.seed),
+            config,
+            resolved_ids: HashMap::new(),
+        })
+    }
+
+    /// Replace the selector with one seeded from `seed`, for deterministic picks.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.selector = SourceSelector::new(Some(seed));
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn config(&self) -> &SourceConfig {
+        &self.config
+    }
+
+    /// Re-read the file if its mtime moved. Returns whether a new config was loaded.
+    /// A broken file keeps the previous config and is not retried until it changes again.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+
+        let raw = fs::read_to_string(&self.path)?;
+        self.config = SourceConfig::parse(&self.path, &raw)?;
+        Ok(true)
+    }
+
+    /// Handles of enabled accounts that have no id yet.
+    pub fn unresolved_handles(&self) -> Vec<String> {
+        self.config
+            .accounts
+            .iter()
+            .filter(|a| a.enabled && a.id.is_none())
+            .filter(|a| !self.resolved_ids.contains_key(&a.handle.to_lowercase()))
+            .map(|a| a.handle.clone())
+            .collect()
+    }
+
+    /// Remember the id looked up for `handle`.
+    pub fn set_resolved_id(&mut self, handle: &str, id: impl Into<String>) {
+        self.resolved_ids.insert(handle.to_lowercase(), id.into());
+    }
+
+    /// Names of all enabled sections.
+    pub fn sections(&self) -> Vec<&str> {
+        self.config
+            .sections
+            .iter()
+            .filter(|s| s.enabled)
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    /// Every enabled account in an enabled section that has an id.
+    pub fn accounts(&self) -> Vec<ResolvedAccount> {
+        let sections = self.sections();
+        self.config
+            .accounts
+            .iter()
+            .filter(|a| a.enabled && sections.contains(&a.section.as_str()))
+            .filter_map(|a| {
+                let id =
+                    a.id.clone()
+                        .or_else(|| self.resolved_ids.get(&a.handle.to_lowercase()).cloned())?;
+                Some(ResolvedAccount {
+                    handle: a.handle.clone(),
+                    id,
+                    section: a.section.clone(),
+                    weight: a.weight,
+                })
+            })
+            .collect()
+    }
+
+    /// Every account plus every search, list, trends and home feed of the enabled sections.
+    pub fn candidates(&self) -> Vec<SourceCandidate> {
+        let mut candidates: Vec<SourceCandidate> = self
+            .accounts()
+            .into_iter()
+            .map(|a| SourceCandidate {
+                key: format!("user:{}", a.id),
+                feed: Feed::User {
+                    handle: a.handle,
+                    id: a.id,
+                },
+                section: a.section,
+                weight: a.weight,
+            })
+            .collect();
+
+        for section in self.config.sections.iter().filter(|s| s.enabled) {
+            let mut feeds: Vec<(String, Feed)> = Vec::new();
+            for query in &section.searches {
+                feeds.push((format!("search:{query}"), Feed::Search(query.clone())));
+            }
+            for list in &section.lists {
+                feeds.push((format!("list:{list}"), Feed::List(list.clone())));
+            }
+            if section.trends {
+                feeds.push(("trends".to_string(), Feed::Trends));
+            }
+            if section.home_timeline {
+                feeds.push(("home".to_string(), Feed::HomeTimeline));
+            }
+            for url in &section.rss {
+                feeds.push((format!("rss:{url}"), Feed::Rss(url.clone())));
+            }
+            for name in &section.subreddits {
+                feeds.push((format!("reddit:{name}"), Feed::Subreddit(name.clone())));
+            }
+            if section.hacker_news {
+                feeds.push(("hn".to_string(), Feed::HackerNews));
+            }
+            for path in &section.tweet_fixtures {
+                feeds.push((format!("fixture:{path}"), Feed::TweetFixture(path.clone())));
+            }
+
+            candidates.extend(feeds.into_iter().map(|(key, feed)| SourceCandidate {
+                key: format!("{}/{key}", section.name),
+                feed,
+                section: section.name.clone(),
+                weight: section.feed_weight,
+            }));
+        }
+        candidates
+    }
+
+    /// Picks the next source to pull from, see `SourceSelector::select`.
+    pub fn select(&mut self) -> Option<SourceCandidate> {
+        self.select_matching(|_| true)
+    }
+
+    /// Like `select`, but only from candidates `keep` accepts,
+    /// e.g. skipping X feeds when we aren't logged in.
+    pub fn select_matching(
+        &mut self,
+        keep: impl Fn(&SourceCandidate) -> bool,
+    ) -> Option<SourceCandidate> {
+        let candidates: Vec<SourceCandidate> =
+            self.candidates().into_iter().filter(|c| keep(c)).collect();
+        self.selector.select(&candidates, &self.config.selector)
+    }
+
+    /// Tell the selector what the last fetch for the candidate `key` found.
+    pub fn record_visit(&mut self, key: &str, newest_tweet_id: Option<&str>) {
+        self.selector.record_visit(key, newest_tweet_id);
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const SOURCES: &str = r#"
+        [[sections]]
+        name = "ai"
+        searches = ["o3"]
+
+        [[sections]]
+        name = "off"
+        enabled = false
+
+        [[accounts]]
+        handle = "sama"
+        id = "1605"
+        section = "ai"
+
+        [[accounts]]
+        handle = "karpathy"
+        section = "ai"
+        weight = 2.0
+    "#;
+
+    /// A registry file `name` in its own temp dir, removed with the dir.
+    fn sources_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sources-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Rewrite `path` a second later than it was, so the change shows even
+    /// where mtimes are coarse.
+    fn rewrite(path: &Path, contents: &str) {
+        let before = modified_time(path).unwrap();
+        fs::write(path, contents).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(before + Duration::from_secs(1))
+            .unwrap();
+    }
+
+    #[test]
+    fn load_reads_toml_and_json() {
+        let config: SourceConfig = toml::from_str(SOURCES).unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+
+        for path in [
+            sources_file("sources.toml", SOURCES),
+            sources_file("sources.json", &json),
+        ] {
+            let mut registry = SourceRegistry::load(&path).unwrap();
+            assert_eq!(registry.sections(), ["ai"]);
+            assert_eq!(registry.unresolved_handles(), ["karpathy"]);
+            assert_eq!(registry.accounts().len(), 1);
+
+            registry.set_resolved_id("Karpathy", "33836629");
+            assert!(registry.unresolved_handles().is_empty());
+            assert_eq!(registry.accounts()[1].id, "33836629");
+            fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn load_fails_on_a_missing_or_invalid_file() {
+        let missing = SourceRegistry::load("/nonexistent/sources.toml")
+            .err()
+            .unwrap();
+        assert!(missing
+            .to_string()
+            .contains("Failed to read source registry"));
+
+        let path = sources_file("invalid.toml", "[[accounts]]\nhandle = 1\n");
+        let invalid = SourceRegistry::load(&path).err().unwrap();
+        assert!(invalid.to_string().contains("Failed to parse"), "{invalid}");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reload_applies_a_changed_file_once() {
+        let path = sources_file("reload.toml", SOURCES);
+        let mut registry = SourceRegistry::load(&path).unwrap();
+        assert!(!registry.reload_if_changed().unwrap());
+
+        let added = format!(
+            "{SOURCES}\n[[accounts]]\nhandle = \"ylecun\"\nid = \"48008938\"\nsection = \"ai\"\n"
+        );
+        rewrite(&path, &added);
+        assert!(registry.reload_if_changed().unwrap());
+        let handles: Vec<String> = registry.accounts().into_iter().map(|a| a.handle).collect();
+        assert_eq!(handles, ["sama", "ylecun"]);
+        assert!(!registry.reload_if_changed().unwrap());
+
+        // A broken edit keeps the last good config and isn't retried until it changes
+        rewrite(&path, "[[sections]\n");
+        assert!(registry.reload_if_changed().is_err());
+        assert!(!registry.reload_if_changed().unwrap());
+        assert_eq!(registry.accounts().len(), 2);
+
+        rewrite(&path, SOURCES);
+        assert!(registry.reload_if_changed().unwrap());
+        assert_eq!(registry.accounts().len(), 1);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_configs_that_never_pick_a_source() {
+        let section = "[[sections]]\nname = \"ai\"\n";
+        let account = |fields: &str| format!("{section}[[accounts]]\nsection = \"ai\"\n{fields}\n");
+        let cases = [
+            (
+                format!(
+                    "{}[[accounts]]\nhandle = \"SAMA\"\nsection = \"ai\"\n",
+                    account("handle = \"sama\"")
+                ),
+                "'SAMA' is listed twice",
+            ),
+            (
+                account("handle = \"sama\"\nweight = -1.0"),
+                "'sama' has an invalid weight",
+            ),
+            (
+                account("handle = \"sama\"\nweight = nan"),
+                "'sama' has an invalid weight",
+            ),
+            (
+                "[[accounts]]\nhandle = \"sama\"\nsection = \"ai\"\n".to_string(),
+                "'sama' uses unknown section 'ai'",
+            ),
+            (
+                format!("{section}feed_weight = -0.5\n"),
+                "'ai' has an invalid feed_weight",
+            ),
+            (
+                format!("{section}feed_weight = inf\n"),
+                "'ai' has an invalid feed_weight",
+            ),
+            (
+                format!("{section}lists = [\"ai-people\"]\n"),
+                "lists must be numeric ids",
+            ),
+            (
+                format!("{section}searches = [\" \"]\n"),
+                "an empty search query",
+            ),
+            (
+                format!("{section}subreddits = [\"r/rust\"]\n"),
+                "a bad subreddit",
+            ),
+        ];
+
+        for (raw, expected) in cases {
+            let error = SourceConfig::parse(Path::new("sources.toml"), &raw)
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains(expected), "{raw}\n{error}");
+        }
+        assert!(SourceConfig::parse(
+            Path::new("sources.toml"),
+            &account("handle = \"sama\"\nweight = 0.0")
+        )
+        .is_ok());
+    }
+}
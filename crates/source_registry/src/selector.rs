@@ -0,0 +1,245 @@
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::SourceCandidate;
+
+/// Tuning for `SourceSelector`, read from the `[selector]` table of the registry file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SelectorConfig {
+    /// A source can't be picked again until this many other cycles have passed.
+    pub cooldown_cycles: u64,
+    /// Weight multiplier for sources we have never visited.
+    pub unvisited_boost: f64,
+    /// Weight multiplier for sources that had nothing new on the last visit.
+    pub stale_penalty: f64,
+    /// Fixed RNG seed, for reproducible picks in tests. Random when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        SelectorConfig {
+            cooldown_cycles: 2,
+            unvisited_boost: 2.0,
+            stale_penalty: 0.25,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Visit {
+    // selection cycle this source was last picked in
+    last_picked: Option<u64>,
+    // whether we've fetched it at all, the newest tweet id seen, and if that moved last time
+    fetched: bool,
+    newest_tweet_id: Option<String>,
+    had_new_tweets: bool,
+}
+
+/// Picks the next source by weight, skipping ones on cooldown and favouring fresh feeds.
+pub struct SourceSelector {
+    rng: StdRng,
+    cycle: u64,
+    visits: HashMap<String, Visit>,
+}
+
+impl SourceSelector {
+    /// A selector seeded from the OS, or from `seed` when given.
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        SourceSelector {
+            rng,
+            cycle: 0,
+            visits: HashMap::new(),
+        }
+    }
+
+    /// Pick one of `candidates` and put it on cooldown.
+    ///
+    /// Effective weight is the candidate weight times a freshness factor:
+    /// `unvisited_boost` if never fetched, `stale_penalty` if the last fetch had
+    /// nothing new, 1.0 otherwise. If every candidate is on cooldown the least
+    /// recently picked one wins.
+    pub fn select(
+        &mut self,
+        candidates: &[SourceCandidate],
+        config: &SelectorConfig,
+    ) -> Option<SourceCandidate> {
+        self.cycle += 1;
+
+        let weighted: Vec<&SourceCandidate> =
+            candidates.iter().filter(|a| a.weight > 0.0).collect();
+        let eligible: Vec<&SourceCandidate> = weighted
+            .iter()
+            .copied()
+            .filter(|a| !self.on_cooldown(&a.key, config.cooldown_cycles))
+            .collect();
+
+        let picked = if eligible.is_empty() {
+            weighted
+                .iter()
+                .copied()
+                .min_by_key(|a| self.visit(&a.key).and_then(|v| v.last_picked))?
+        } else {
+            let scored: Vec<(&SourceCandidate, f64)> = eligible
+                .iter()
+                .map(|a| (*a, a.weight * self.freshness(&a.key, config)))
+                .collect();
+            scored
+                .choose_weighted(&mut self.rng, |(_, weight)| *weight)
+                .ok()
+                .map(|(a, _)| *a)?
+        };
+
+        self.visits
+            .entry(picked.key.clone())
+            .or_default()
+            .last_picked = Some(self.cycle);
+        Some(picked.clone())
+    }
+
+    /// Record what a fetch for candidate `key` returned, so the next pick knows if it's stale.
+    pub fn record_visit(&mut self, key: &str, newest_tweet_id: Option<&str>) {
+        let visit = self.visits.entry(key.to_string()).or_default();
+        visit.fetched = true;
+        visit.had_new_tweets = match (newest_tweet_id, visit.newest_tweet_id.as_deref()) {
+            (Some(newest), Some(previous)) => newest != previous,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if let Some(newest) = newest_tweet_id {
+            visit.newest_tweet_id = Some(newest.to_string());
+        }
+    }
+
+    fn visit(&self, key: &str) -> Option<&Visit> {
+        self.visits.get(key)
+    }
+
+    fn on_cooldown(&self, key: &str, cooldown_cycles: u64) -> bool {
+        self.visit(key)
+            .and_then(|v| v.last_picked)
+            .is_some_and(|last| self.cycle - last <= cooldown_cycles)
+    }
+
+    fn freshness(&self, key: &str, config: &SelectorConfig) -> f64 {
+        match self.visit(key) {
+            Some(v) if !v.fetched => config.unvisited_boost,
+            Some(v) if v.had_new_tweets => 1.0,
+            Some(_) => config.stale_penalty,
+            None => config.unvisited_boost,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Feed;
+
+    fn candidate(key: &str, weight: f64) -> SourceCandidate {
+        SourceCandidate {
+            key: key.to_string(),
+            feed: Feed::Search(key.to_string()),
+            section: "test".to_string(),
+            weight,
+        }
+    }
+
+    fn config(cooldown_cycles: u64) -> SelectorConfig {
+        SelectorConfig {
+            cooldown_cycles,
+            ..SelectorConfig::default()
+        }
+    }
+
+    fn picks(
+        selector: &mut SourceSelector,
+        candidates: &[SourceCandidate],
+        config: &SelectorConfig,
+        n: usize,
+    ) -> Vec<String> {
+        (0..n)
+            .map(|_| selector.select(candidates, config).unwrap().key)
+            .collect()
+    }
+
+    #[test]
+    fn a_seed_repeats_the_same_picks() {
+        let candidates: Vec<_> = ["a", "b", "c", "d"].map(|k| candidate(k, 1.0)).into();
+        let config = config(0);
+
+        let first = picks(&mut SourceSelector::new(Some(7)), &candidates, &config, 50);
+        let second = picks(&mut SourceSelector::new(Some(7)), &candidates, &config, 50);
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn a_picked_source_waits_out_its_cooldown() {
+        let candidates: Vec<_> = ["a", "b", "c", "d"].map(|k| candidate(k, 1.0)).into();
+        let mut selector = SourceSelector::new(Some(1));
+
+        let picked = picks(&mut selector, &candidates, &config(2), 60);
+
+        for window in picked.windows(3) {
+            assert!(
+                window[0] != window[1] && window[0] != window[2] && window[1] != window[2],
+                "picked a source again within its cooldown: {window:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_least_recently_picked_wins_when_all_are_cooling_down() {
+        let candidates: Vec<_> = ["a", "b", "c"].map(|k| candidate(k, 1.0)).into();
+        let mut selector = SourceSelector::new(Some(3));
+
+        let picked = picks(&mut selector, &candidates, &config(5), 6);
+
+        // The first three are all different, then they come back in the same order
+        let mut first = picked[..3].to_vec();
+        first.sort();
+        assert_eq!(first, ["a", "b", "c"]);
+        assert_eq!(picked[3..], picked[..3]);
+    }
+
+    #[test]
+    fn picks_follow_the_weights() {
+        let candidates = [
+            candidate("heavy", 9.0),
+            candidate("light", 1.0),
+            candidate("off", 0.0),
+        ];
+        let mut selector = SourceSelector::new(Some(42));
+
+        let picked = picks(&mut selector, &candidates, &config(0), 1000);
+
+        let heavy = picked.iter().filter(|k| *k == "heavy").count();
+        assert!((850..=950).contains(&heavy), "heavy picked {heavy} of 1000");
+        assert!(!picked.iter().any(|k| k == "off"));
+    }
+
+    #[test]
+    fn a_stale_source_is_picked_less() {
+        let candidates = [candidate("stale", 1.0), candidate("fresh", 1.0)];
+        let mut selector = SourceSelector::new(Some(42));
+        for (key, ids) in [("stale", ["1", "1"]), ("fresh", ["1", "2"])] {
+            for id in ids {
+                selector.record_visit(key, Some(id));
+            }
+        }
+
+        let picked = picks(&mut selector, &candidates, &config(0), 1000);
+
+        // 0.25 against 1.0, so a fifth of the picks
+        let stale = picked.iter().filter(|k| *k == "stale").count();
+        assert!((150..=250).contains(&stale), "stale picked {stale} of 1000");
+    }
+}
@@ -1,34 +1,54 @@
 use anyhow::anyhow;
 use anyhow::Result;
 use regex::Regex;
-use std::{env, fs, path::Path, process::Command};
+use run_workspace::RunWorkspace;
+use std::{fs, path::Path, process::Command};
+use tracing::{info, info_span};
 
-/// Runs the whisperx Docker container to create an SRT file, using `audio_path`.
-pub fn run_docker_transcription(id: &str, audio_path: &str) -> Result<()> {
+// Where the run directory is mounted inside the container.
+const CONTAINER_RUN_DIR: &str = "/run_data";
+
+/// Runs the whisperx Docker container `image` to create the run's transcription SRT,
+/// using `audio_path`. `docker` is the docker binary.
+///
+/// Only the run directory is mounted, by absolute path, so this works from any
+/// working directory and with the data root anywhere on disk.
+pub fn run_docker_transcription(
+    workspace: &RunWorkspace,
+    audio_path: &str,
+    docker: &str,
+    image: &str,
+) -> Result<()> {
     let audio_file_name = Path::new(audio_path)
         .file_name()
         .ok_or_else(|| anyhow!("Could not extract filename from audio path"))?
         .to_str()
         .ok_or_else(|| anyhow!("Invalid UTF-8 in audio filename"))?;
 
-    let current_dir = env::current_dir()?;
-    let current_dir_str = current_dir
+    // Create the run directory if it doesn't exist
+    fs::create_dir_all(workspace.dir())?;
+    let run_dir = fs::canonicalize(workspace.dir())?;
+    let run_dir_str = run_dir
         .to_str()
-        .ok_or_else(|| anyhow!("Invalid current directory string"))?;
-
-    // Create tmp directory if it doesn't exist
-    fs::create_dir_all("./tmp")?;
+        .ok_or_else(|| anyhow!("Invalid UTF-8 in run directory {}", run_dir.display()))?;
 
-    let output_dir = format!("./tmp/{}", id);
+    if !run_dir.join(audio_file_name).exists() {
+        return Err(anyhow!(
+            "{audio_path} is not in the run directory {}, whisperx can't see it",
+            run_dir.display()
+        ));
+    }
+    let container_audio = format!("{CONTAINER_RUN_DIR}/{audio_file_name}");
 
-    println!("Running Docker command to generate SRT for '{audio_file_name}'...");
-    let status = Command::new("docker")
+    let _span = info_span!("transcription", provider = "whisperx", image).entered();
+    info!("Running Docker command to generate SRT for '{audio_file_name}'...");
+    let status = Command::new(docker)
         .args([
             "run",
-            "-it",
+            "--rm",
             "-v",
-            &format!("{current_dir_str}:/app"),
-            "whisperx:large-v3-en",
+            &format!("{run_dir_str}:{CONTAINER_RUN_DIR}"),
+            image,
             "--",
             "--device",
             "cpu",
@@ -39,14 +59,23 @@ pub fn run_docker_transcription(id: &str, audio_path: &str) -> Result<()> {
             "--output_format",
             "srt",
             "--output_dir",
-            &output_dir,
-            audio_path,
+            CONTAINER_RUN_DIR,
+            &container_audio,
         ])
         .status()?;
 
     if !status.success() {
         return Err(anyhow!("Docker command failed with status: {status:?}"));
     }
+    workspace.record(
+        workspace.transcription_srt(),
+        "transcription",
+        &[
+            ("image", image),
+            ("audio", audio_file_name),
+            ("compute_type", "float32"),
+        ],
+    )?;
 
     // This is wrong
     //let srt_path = format!("./tmp/{}.srt", audio_file_name);
@@ -54,11 +83,60 @@ pub fn run_docker_transcription(id: &str, audio_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn convert(id: &str, input_path: &str) -> Result<String> {
+/// Writes the run's transcription SRT without whisperx, for offline runs: the
+/// words of `text` spread evenly over `seconds`, in whisperx's format of the
+/// whole line with the current word highlighted.
+pub fn write_offline_transcription(
+    workspace: &RunWorkspace,
+    text: &str,
+    seconds: f32,
+) -> Result<()> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Err(anyhow!("Nothing to transcribe, the text is empty"));
+    }
+    let word_duration = seconds / words.len() as f32;
+
+    let mut blocks = Vec::new();
+    for i in 0..words.len() {
+        let line: Vec<String> = words
+            .iter()
+            .enumerate()
+            .map(|(j, w)| {
+                if i == j {
+                    format!("<u>{w}</u>")
+                } else {
+                    w.to_string()
+                }
+            })
+            .collect();
+        blocks.push(format!(
+            "{}\n{} --> {}\n{}\n",
+            i + 1,
+            format_srt_timestamp(i as f32 * word_duration),
+            format_srt_timestamp((i + 1) as f32 * word_duration),
+            line.join(" ")
+        ));
+    }
+
+    fs::create_dir_all(workspace.dir())?;
+    let srt_path = workspace.transcription_srt();
+    fs::write(&srt_path, blocks.join("\n"))?;
+    workspace.record(&srt_path, "transcription", &[("provider", "offline")])?;
+    info!(
+        "Wrote offline transcription of {} words to {}",
+        words.len(),
+        srt_path.display()
+    );
+    Ok(())
+}
+
+/// Keep only the highlighted word of each whisperx entry, written to the run's `final_srt.srt`.
+pub fn convert(workspace: &RunWorkspace, input_path: &str) -> Result<String> {
     // Read the entire input file.
-    println!("Reading input file: {input_path}");
+    info!("Reading input file: {input_path}");
     let content = fs::read_to_string(input_path)?;
-    println!("Finished Reading input file: {input_path}");
+    info!("Finished Reading input file: {input_path}");
 
     // Split into blocks. SRT blocks are separated by one or more blank lines.
     let block_re = Regex::new(r"\r?\n\r?\n")?;
@@ -111,11 +189,12 @@ pub fn convert(id: &str, input_path: &str) -> Result<String> {
     // Join the new entries with an empty line between blocks.
     let output_content = new_entries.join("\n");
     // let srt_output_path = format!("{input_path}");
-    let srt_output_path = format!("./tmp/{}/final_srt.srt", id);
+    let srt_output_path = workspace.final_srt().to_string_lossy().to_string();
     //let srt_output_path = format!("{input_path}");
-    println!("Writing output to: {}", srt_output_path);
+    info!("Writing output to: {}", srt_output_path);
     fs::write(srt_output_path.clone(), output_content)?;
-    println!("Finished writing output to: {}", srt_output_path);
+    info!("Finished writing output to: {}", srt_output_path);
+    workspace.record(&srt_output_path, "subtitles", &[("source", input_path)])?;
 
     Ok(srt_output_path)
 }
@@ -180,3 +259,15 @@ pub fn parse_srt_timestamp(timestamp: &str) -> Result<f32> {
     let millis: f32 = ms_part.parse()?;
     Ok(hours * 3600.0 + minutes * 60.0 + seconds + millis / 1000.0)
 }
+
+/// Format seconds as an "HH:MM:SS,mmm" SRT timestamp.
+pub fn format_srt_timestamp(seconds: f32) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
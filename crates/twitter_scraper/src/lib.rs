@@ -1,40 +1,136 @@
-use agent_twitter_client::{scraper::Scraper, tweets::create_tweet_request};
+use agent_twitter_client::{scraper::Scraper, search::SearchMode, tweets::create_tweet_request};
 use anyhow::anyhow;
 use anyhow::Result;
-use rand::prelude::*;
-use std::env;
+use source_registry::{Feed, SourceRegistry};
+use std::collections::HashSet;
 use std::fs;
+use tracing::{info, warn};
 
-/// Logs into Twitter via the Scraper
-pub async fn init_scraper() -> Result<Scraper> {
-    let username = env::var("TWITTER_USERNAME").map_err(|_| anyhow!("TWITTER_USERNAME not set"))?;
-    let password = env::var("TWITTER_PASSWORD").map_err(|_| anyhow!("TWITTER_PASSWORD not set"))?;
-    let email = "beginbot@gmail.com".to_string();
+pub mod seen;
+pub mod session;
+pub mod source_tweet;
 
-    let mut scraper = Scraper::new().await?;
-    scraper.login(username, password, Some(email), None).await?;
-    Ok(scraper)
+pub use seen::SeenTweetStore;
+pub use session::{TwitterCredentials, TwitterSession};
+pub use source_tweet::{rank_by_engagement, Media, MediaKind, SourceTweet};
+
+/// Looks up ids for registry handles that don't have one yet.
+/// A handle that can't be resolved is logged and skipped, it just won't be picked.
+pub async fn resolve_handles(scraper: &Scraper, registry: &mut SourceRegistry) -> Result<()> {
+    for handle in registry.unresolved_handles() {
+        match scraper.get_user_id(&handle).await {
+            Ok(id) => {
+                info!("Resolved @{handle} to user_id '{id}'");
+                registry.set_resolved_id(&handle, id);
+            }
+            Err(e) => warn!("Could not resolve @{handle}: {e}"),
+        }
+    }
+    Ok(())
 }
 
 // This goes in the scraper
-/// Given a user_id, fetches the latest tweets and returns them joined as a single string.
-pub async fn fetch_tweets(scraper: &mut Scraper, user_id: &str) -> Result<String> {
+/// Given a user_id, fetches the latest page of tweets.
+pub async fn fetch_tweets(scraper: &mut Scraper, user_id: &str) -> Result<Vec<SourceTweet>> {
     let tweets = scraper.get_user_tweets(user_id, 1, None).await?;
-    let text = tweets
+    Ok(tweets
+        .tweets
+        .iter()
+        .filter_map(SourceTweet::from_tweet)
+        .collect())
+}
+
+// How many tweets to ask for per search / list / home timeline fetch.
+const FEED_PAGE_SIZE: i32 = 40;
+// How many of the current trends to search when a section pulls from trends.
+const TRENDS_TO_SEARCH: usize = 3;
+const TWEETS_PER_TREND: i32 = 15;
+
+/// Fetches the latest tweets for any X feed.
+///
+/// * Searches use "Top" results so we get what's being talked about, not just what's newest.
+/// * Lists go through the `list:<id>` search operator.
+/// * Trends searches the first few trending topics and merges the results.
+/// * The home timeline is the logged-in account's "For you" feed.
+pub async fn fetch_feed(scraper: &mut Scraper, feed: &Feed) -> Result<Vec<SourceTweet>> {
+    match feed {
+        Feed::User { id, .. } => fetch_tweets(scraper, id).await,
+        Feed::Search(query) => search(scraper, query, FEED_PAGE_SIZE, SearchMode::Top).await,
+        Feed::List(list_id) => {
+            search(
+                scraper,
+                &format!("list:{list_id}"),
+                FEED_PAGE_SIZE,
+                SearchMode::Latest,
+            )
+            .await
+        }
+        Feed::Trends => {
+            let trends = scraper.get_trends().await?;
+            info!("Current trends: {}", trends.join(", "));
+
+            let mut tweets = Vec::new();
+            for trend in trends.iter().take(TRENDS_TO_SEARCH) {
+                match search(scraper, trend, TWEETS_PER_TREND, SearchMode::Top).await {
+                    Ok(found) => tweets.extend(found),
+                    Err(e) => warn!("Search for trend '{trend}' failed: {e}"),
+                }
+            }
+            Ok(dedup_by_id(tweets))
+        }
+        Feed::HomeTimeline => {
+            let results = scraper
+                .get_home_timeline(FEED_PAGE_SIZE, Vec::new())
+                .await?;
+            Ok(results
+                .iter()
+                .filter_map(SourceTweet::from_timeline_json)
+                .collect())
+        }
+        Feed::Rss(_) | Feed::Subreddit(_) | Feed::HackerNews | Feed::TweetFixture(_) => {
+            Err(anyhow!("{feed} is not an X feed"))
+        }
+    }
+}
+
+async fn search(
+    scraper: &Scraper,
+    query: &str,
+    max: i32,
+    mode: SearchMode,
+) -> Result<Vec<SourceTweet>> {
+    let tweets = scraper.search_tweets(query, max, mode, None).await?;
+    Ok(tweets
         .tweets
         .iter()
-        .filter_map(|t| t.text.clone())
-        .collect::<Vec<String>>()
-        .join("\n");
-    Ok(text)
+        .filter_map(SourceTweet::from_tweet)
+        .collect())
 }
 
-/// Posts the tweet text with a local MP4 file as media.
+/// Drop repeated tweets, e.g. the same tweet showing up under two trends.
+fn dedup_by_id(tweets: Vec<SourceTweet>) -> Vec<SourceTweet> {
+    let mut ids = HashSet::new();
+    tweets
+        .into_iter()
+        .filter(|t| ids.insert(t.id.clone()))
+        .collect()
+}
+
+/// The id of the most recent tweet, by date and then by id.
+/// Tweet ids are snowflakes, so the largest is the newest; other sources fall back to the date.
+pub fn newest_tweet_id(tweets: &[SourceTweet]) -> Option<String> {
+    tweets
+        .iter()
+        .max_by_key(|t| (t.created_at, t.id.parse::<u64>().unwrap_or(0)))
+        .map(|t| t.id.clone())
+}
+
+/// Posts the tweet text with a local MP4 file as media, returning the new tweet's id.
 pub async fn post_tweet_with_video(
     scraper: &Scraper,
     tweet_text: &str,
     video_path: &str,
-) -> Result<()> {
+) -> Result<String> {
     let mp4_bytes = fs::read(video_path)?;
     let media_data = vec![(mp4_bytes, String::from("video/mp4"))];
 
@@ -46,49 +142,9 @@ pub async fn post_tweet_with_video(
     )
     .await?;
 
-    println!("Tweet posted successfully: {response}");
-    Ok(())
-}
-
-// =================================================================
-
-const SECTION_CRYPTO: &str = "crypto";
-const SECTION_AI: &str = "ai";
-const SECTION_FUNNY: &str = "funny";
-
-const TOPICS: [&str; 3] = [SECTION_CRYPTO, SECTION_AI, SECTION_FUNNY];
-
-const CRYPTO_USER_IDS: &[&str] = &[
-    "5925542",  // casey
-    "40134343", // tylerh
-];
-
-const AI_USER_IDS: &[&str] = &[
-    "1005182149",          // begin
-    "427089628",           // lex
-    "1605",                // sama
-    "1173552893003255808", // yacine
-    "1326180756310331399", // tom dorr
-];
-
-const FUNNY_USER_IDS: &[&str] = &[
-    "16298441", // dril
-               // We need more
-];
-
-/// Picks a random section name ("crypto", "ai", or "funny") and a random user ID from that section.
-pub fn get_random_user_id() -> Option<(&'static str, &'static str)> {
-    let mut rng = rand::rng();
-    let section = TOPICS.choose(&mut rng).copied()?;
-
-    // Pick a random ID from that section
-    let user_ids = match section {
-        SECTION_CRYPTO => CRYPTO_USER_IDS,
-        SECTION_AI => AI_USER_IDS,
-        SECTION_FUNNY => FUNNY_USER_IDS,
-        _ => return None,
-    };
-
-    let user_id = user_ids.choose(&mut rng).copied()?;
-    Some((section, user_id))
+    info!("Tweet posted successfully: {response}");
+    let tweet_id = response["data"]["create_tweet"]["tweet_results"]["result"]["rest_id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("No tweet id in create tweet response: {response}"))?;
+    Ok(tweet_id.to_string())
 }
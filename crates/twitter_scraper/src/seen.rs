@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::SourceTweet;
+
+// Forget tweets after this long so the file doesn't grow forever.
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Tweet ids we've already fed to the summarizer, persisted as JSON.
+pub struct SeenTweetStore {
+    path: PathBuf,
+    // tweet id -> unix timestamp it was first seen
+    seen: HashMap<String, i64>,
+    retention_days: i64,
+}
+
+impl SeenTweetStore {
+    /// Load the store from `path`, starting empty if the file doesn't exist yet.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let seen = if path.exists() {
+            let raw = fs::read_to_string(&path)?;
+            serde_json::from_str(&raw)
+                .map_err(|e| anyhow!("Failed to parse seen tweets {}: {e}", path.display()))?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(SeenTweetStore {
+            path,
+            seen,
+            retention_days: DEFAULT_RETENTION_DAYS,
+        })
+    }
+
+    pub fn with_retention_days(mut self, retention_days: i64) -> Self {
+        self.retention_days = retention_days;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, tweet_id: &str) -> bool {
+        self.seen.contains_key(tweet_id)
+    }
+
+    /// Drop every tweet we've already seen.
+    pub fn filter_unseen(&self, tweets: Vec<SourceTweet>) -> Vec<SourceTweet> {
+        tweets
+            .into_iter()
+            .filter(|t| !self.contains(&t.id))
+            .collect()
+    }
+
+    /// Remember `tweets` and write the store back to disk.
+    pub fn mark_seen(&mut self, tweets: &[SourceTweet]) -> Result<()> {
+        let now = Utc::now().timestamp();
+        for tweet in tweets {
+            self.seen.entry(tweet.id.clone()).or_insert(now);
+        }
+        self.save()
+    }
+
+    /// Prune expired ids and write to a temp file first so a crash can't truncate the store.
+    fn save(&mut self) -> Result<()> {
+        let cutoff = Utc::now().timestamp() - self.retention_days * 24 * 60 * 60;
+        self.seen.retain(|_, first_seen| *first_seen >= cutoff);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&self.seen)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use agent_twitter_client::scraper::Scraper;
+use anyhow::Result;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Login details. X asks for `email` when it wants to confirm a login, and
+/// `two_factor_secret` is the TOTP secret of accounts with 2FA on.
+#[derive(Clone)]
+pub struct TwitterCredentials {
+    pub username: String,
+    pub password: String,
+    pub email: Option<String>,
+    pub two_factor_secret: Option<String>,
+}
+
+/// A logged-in scraper whose cookies are saved to disk, so restarts reuse the
+/// session instead of doing a full login (which X rate limits and flags).
+pub struct TwitterSession {
+    scraper: Scraper,
+    credentials: TwitterCredentials,
+    cookie_path: PathBuf,
+}
+
+impl TwitterSession {
+    /// Restore the saved cookies if they still work, otherwise log in and save new ones.
+    pub async fn start(
+        credentials: TwitterCredentials,
+        cookie_path: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        let mut session = TwitterSession {
+            scraper: Scraper::new().await?,
+            credentials,
+            cookie_path: cookie_path.into(),
+        };
+
+        if session.restore_cookies().await {
+            info!(
+                "Restored Twitter session from {}",
+                session.cookie_path.display()
+            );
+        } else {
+            session.login().await?;
+        }
+        Ok(session)
+    }
+
+    pub fn scraper(&self) -> &Scraper {
+        &self.scraper
+    }
+
+    pub fn scraper_mut(&mut self) -> &mut Scraper {
+        &mut self.scraper
+    }
+
+    pub fn cookie_path(&self) -> &Path {
+        &self.cookie_path
+    }
+
+    /// Check the session and log in again if X dropped it.
+    /// Returns whether a fresh login was needed.
+    pub async fn ensure_logged_in(&mut self) -> Result<bool> {
+        if self.scraper.is_logged_in().await.unwrap_or(false) {
+            return Ok(false);
+        }
+        info!("Twitter session expired, logging in again");
+        self.login().await?;
+        Ok(true)
+    }
+
+    /// Full username/password login, then save the cookies for next time.
+    pub async fn login(&mut self) -> Result<()> {
+        let credentials = self.credentials.clone();
+        // Start from a clean client so stale cookies don't get sent with the login flow
+        self.scraper = Scraper::new().await?;
+        self.scraper
+            .login(
+                credentials.username,
+                credentials.password,
+                credentials.email,
+                credentials.two_factor_secret,
+            )
+            .await?;
+        info!("Logged into Twitter as @{}", self.credentials.username);
+
+        // Not fatal: we're logged in, we just can't skip the login next start
+        if let Err(e) = self.save_cookies().await {
+            warn!(
+                "Could not save Twitter cookies to {}: {e}",
+                self.cookie_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    async fn save_cookies(&self) -> Result<()> {
+        let cookies = self.scraper.get_cookie_string().await?;
+        write_private(&self.cookie_path, &cookies)
+    }
+
+    /// Load saved cookies into the scraper. Returns whether they gave us a live session.
+    async fn restore_cookies(&mut self) -> bool {
+        let Ok(cookies) = fs::read_to_string(&self.cookie_path) else {
+            return false;
+        };
+        if cookies.trim().is_empty() {
+            return false;
+        }
+        if let Err(e) = self.scraper.set_from_cookie_string(cookies.trim()).await {
+            warn!(
+                "Ignoring unreadable Twitter cookies in {}: {e}",
+                self.cookie_path.display()
+            );
+            return false;
+        }
+        self.scraper.is_logged_in().await.unwrap_or(false)
+    }
+}
+
+/// Replace `path` with `contents` through a tmp file that only the owner can read. The cookies
+/// are as good as the password, so the file is private from the moment it exists, and a
+/// leftover tmp file with other permissions is removed rather than reused.
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    match fs::remove_file(&tmp_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(&tmp_path)?.write_all(contents.as_bytes())?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn cookies_are_written_private() {
+        let dir = std::env::temp_dir().join(format!("cookies-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("twitter_cookies.txt");
+        let stale = path.with_extension("tmp");
+        fs::write(&stale, "old").unwrap();
+        fs::set_permissions(&stale, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, "auth_token=secret").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "auth_token=secret");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!stale.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+use agent_twitter_client::models::Tweet;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Photo,
+    Video,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Media {
+    pub kind: MediaKind,
+    pub url: String,
+    pub alt_text: Option<String>,
+}
+
+/// A tweet with the context the summarizer cares about: who, when, how it did,
+/// what it quotes or replies to, and what media it carries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceTweet {
+    pub id: String,
+    pub author: String,
+    pub author_name: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub text: String,
+    pub likes: u32,
+    pub retweets: u32,
+    pub replies: u32,
+    pub views: Option<u32>,
+    pub quoted: Option<Box<SourceTweet>>,
+    pub reply_to: Option<Box<SourceTweet>>,
+    pub reply_to_id: Option<String>,
+    /// Set when this is a retweet: the fields above describe the original tweet.
+    pub retweeted_by: Option<String>,
+    pub media: Vec<Media>,
+    pub url: Option<String>,
+}
+
+impl SourceTweet {
+    /// Convert a scraped tweet. Returns `None` for tweets without an id or text.
+    /// Retweets are unwrapped to the original tweet so engagement and text are the real ones.
+    pub fn from_tweet(tweet: &Tweet) -> Option<Self> {
+        if tweet.is_retweet.unwrap_or(false) {
+            if let Some(original) = tweet.retweeted_status.as_deref() {
+                let mut source = SourceTweet::from_tweet(original)?;
+                source.retweeted_by = tweet.username.clone();
+                return Some(source);
+            }
+        }
+
+        let id = tweet.id.clone()?;
+        let text = tweet.text.clone().filter(|t| !t.trim().is_empty())?;
+
+        let media = tweet
+            .photos
+            .iter()
+            .map(|p| Media {
+                kind: MediaKind::Photo,
+                url: p.url.clone(),
+                alt_text: p.alt_text.clone(),
+            })
+            .chain(tweet.videos.iter().map(|v| Media {
+                kind: MediaKind::Video,
+                url: v.url.clone().unwrap_or_else(|| v.preview.clone()),
+                alt_text: None,
+            }))
+            .collect();
+
+        Some(SourceTweet {
+            id,
+            author: tweet.username.clone().unwrap_or_default(),
+            author_name: tweet.name.clone(),
+            created_at: tweet.time_parsed,
+            text,
+            likes: count(tweet.likes),
+            retweets: count(tweet.retweets),
+            replies: count(tweet.replies),
+            views: tweet.views.map(|v| v.max(0) as u32),
+            quoted: tweet
+                .quoted_status
+                .as_deref()
+                .and_then(SourceTweet::from_tweet)
+                .map(Box::new),
+            reply_to: tweet
+                .in_reply_to_status
+                .as_deref()
+                .and_then(SourceTweet::from_tweet)
+                .map(Box::new),
+            reply_to_id: tweet.in_reply_to_status_id.clone(),
+            retweeted_by: None,
+            media,
+            url: tweet.permanent_url.clone(),
+        })
+    }
+
+    /// Convert one raw `tweet_results.result` object, as returned by the home timeline.
+    /// Returns `None` for promoted/empty entries without an id or text.
+    pub fn from_timeline_json(result: &Value) -> Option<Self> {
+        // Tweets with visibility limits wrap the real tweet one level down.
+        let result = match result["__typename"].as_str() {
+            Some("TweetWithVisibilityResults") => &result["tweet"],
+            _ => result,
+        };
+        let legacy = &result["legacy"];
+
+        if let Some(original) = legacy["retweeted_status_result"]["result"].as_object() {
+            let mut source = SourceTweet::from_timeline_json(&Value::Object(original.clone()))?;
+            source.retweeted_by = screen_name(result);
+            return Some(source);
+        }
+
+        let id = result["rest_id"].as_str()?.to_string();
+        let text = legacy["full_text"]
+            .as_str()
+            .filter(|t| !t.trim().is_empty())?
+            .to_string();
+        let author = screen_name(result).unwrap_or_default();
+
+        let media = legacy["extended_entities"]["media"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| {
+                let kind = match m["type"].as_str()? {
+                    "photo" => MediaKind::Photo,
+                    _ => MediaKind::Video,
+                };
+                Some(Media {
+                    kind,
+                    url: m["media_url_https"].as_str()?.to_string(),
+                    alt_text: m["ext_alt_text"].as_str().map(str::to_string),
+                })
+            })
+            .collect();
+
+        Some(SourceTweet {
+            url: Some(format!("https://x.com/{author}/status/{id}")),
+            id,
+            author_name: result["core"]["user_results"]["result"]["legacy"]["name"]
+                .as_str()
+                .map(str::to_string),
+            author,
+            created_at: legacy["created_at"].as_str().and_then(|t| {
+                DateTime::parse_from_str(t, "%a %b %d %H:%M:%S %z %Y")
+                    .ok()
+                    .map(|t| t.with_timezone(&Utc))
+            }),
+            text,
+            likes: json_count(&legacy["favorite_count"]),
+            retweets: json_count(&legacy["retweet_count"]),
+            replies: json_count(&legacy["reply_count"]),
+            // views come back as a string, e.g. "12345"
+            views: result["views"]["count"]
+                .as_str()
+                .and_then(|v| v.parse().ok()),
+            quoted: SourceTweet::from_timeline_json(&result["quoted_status_result"]["result"])
+                .map(Box::new),
+            reply_to: None,
+            reply_to_id: legacy["in_reply_to_status_id_str"]
+                .as_str()
+                .map(str::to_string),
+            retweeted_by: None,
+            media,
+        })
+    }
+
+    /// Rough "how much is this being talked about" number.
+    /// Retweets and replies spread a tweet further than a like, so they count for more.
+    pub fn engagement_score(&self) -> f64 {
+        let views = self.views.unwrap_or(0) as f64;
+        self.likes as f64 + 3.0 * self.retweets as f64 + 2.0 * self.replies as f64 + views / 1000.0
+    }
+
+    /// Render the tweet, its reply context and quoted tweet as plain text for a prompt.
+    pub fn to_prompt_block(&self) -> String {
+        let mut block = String::new();
+
+        let _ = write!(block, "@{}", self.author);
+        if let Some(name) = &self.author_name {
+            let _ = write!(block, " ({name})");
+        }
+        if let Some(created_at) = self.created_at {
+            let _ = write!(block, " · {}", created_at.format("%Y-%m-%d %H:%M UTC"));
+        }
+        let _ = write!(
+            block,
+            " · {} likes · {} retweets · {} replies",
+            self.likes, self.retweets, self.replies
+        );
+        if let Some(retweeted_by) = &self.retweeted_by {
+            let _ = write!(block, " · retweeted by @{retweeted_by}");
+        }
+        block.push('\n');
+
+        if let Some(parent) = &self.reply_to {
+            let _ = writeln!(
+                block,
+                "in reply to @{}: {}",
+                parent.author,
+                one_line(&parent.text)
+            );
+        } else if self.reply_to_id.is_some() {
+            block.push_str("(reply in a thread)\n");
+        }
+
+        block.push_str(self.text.trim());
+        block.push('\n');
+
+        if let Some(quoted) = &self.quoted {
+            let _ = writeln!(
+                block,
+                "  > quoting @{}: {}",
+                quoted.author,
+                one_line(&quoted.text)
+            );
+        }
+
+        let photos = self
+            .media
+            .iter()
+            .filter(|m| m.kind == MediaKind::Photo)
+            .count();
+        let videos = self.media.len() - photos;
+        if !self.media.is_empty() {
+            let _ = writeln!(block, "  [media: {photos} photos, {videos} videos]");
+        }
+        for alt_text in self.media.iter().filter_map(|m| m.alt_text.as_deref()) {
+            let _ = writeln!(block, "  [image: {}]", one_line(alt_text));
+        }
+
+        block
+    }
+}
+
+/// Most engaged first.
+pub fn rank_by_engagement(mut tweets: Vec<SourceTweet>) -> Vec<SourceTweet> {
+    tweets.sort_by(|a, b| b.engagement_score().total_cmp(&a.engagement_score()));
+    tweets
+}
+
+fn count(value: Option<i32>) -> u32 {
+    value.unwrap_or(0).max(0) as u32
+}
+
+fn json_count(value: &Value) -> u32 {
+    value.as_u64().unwrap_or(0).min(u32::MAX as u64) as u32
+}
+
+fn screen_name(result: &Value) -> Option<String> {
+    result["core"]["user_results"]["result"]["legacy"]["screen_name"]
+        .as_str()
+        .map(str::to_string)
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
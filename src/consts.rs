@@ -1,13 +0,0 @@
-#![allow(dead_code)]
-
-pub const DEFAULT_PROMPT_MODEL: &str = "o3-mini"; // or "gpt-4o", whichever you prefer
-                                                  //
-pub const MODEL_NAME: &str = "o3-mini"; // or "gpt-4o", whichever you prefer
-                                        //
-pub const TWEET_WRITER_PROMPT: &str = "You write funny original tweets. Be grounded in reality. don't ever mention tweeting. No Poems ever. You never use emojis. You don't capitalize or use !'s. You use internet humor. Never use hashtags. Add spacing between lines.";
-
-pub const TWEET_SUMMARIZER_PROMPT: &str = "Given a collection of tweets, create a topic. \
-Be specific, use context from the Tweets. Don't be generic. Use quotes and words from the Tweet. \
-Don't be boring. Do not be generic. Make the topic specific. Instead of Tech, name the Tech. \
-Make it a short concise topic. Be detailed. No more than 5 words. Pick out individual stories \
-and tweets. Be specific. Format your answer exactly as follows:\nTopic: <your topic here>";
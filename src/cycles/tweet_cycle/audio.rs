@@ -1,11 +1,35 @@
 // Audio.rs
 use agent_twitter_client::scraper::Scraper;
 use anyhow::{anyhow, Result};
+use bot_config::{Config, TtsProvider};
 use rig::{completion::Prompt, providers};
+use run_workspace::RunWorkspace;
 use std::{fs, path::Path};
+use tracing::info;
 
-pub async fn generate_audio(id: &str, tweet_text: &str) -> Result<String> {
-    let audio_path = elevenlabs_lab::save_tts_audio(id, tweet_text).await?;
-    println!("Tweet audio saved to: {audio_path}");
+use crate::cycles::tweet_cycle::{text_action, workers};
+
+/// Read the tweet out with the configured TTS provider. The offline one is a
+/// tone as long as the voice would take, at least a second.
+pub async fn generate_audio(
+    workspace: &RunWorkspace,
+    config: &Config,
+    tweet_text: &str,
+) -> Result<String> {
+    let audio_path = match config.providers.tts {
+        TtsProvider::ElevenLabs => {
+            elevenlabs_lab::save_tts_audio(workspace, config.keys.elevenlabs()?, tweet_text).await?
+        }
+        TtsProvider::Offline => {
+            let workspace = workspace.clone();
+            let ffmpeg = config.tools.ffmpeg.clone();
+            let seconds = text_action::estimate_spoken_seconds(tweet_text).max(1.0);
+            workers::run_blocking(move || {
+                ffmpeg_wrapper::save_placeholder_audio(&workspace, &ffmpeg, seconds)
+            })
+            .await??
+        }
+    };
+    info!("Tweet audio saved to: {audio_path}");
     Ok(audio_path)
 }
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, Result};
+use bot_config::Config;
+use openai_wrapper::{Models, Role};
+use prompt_library::PromptSet;
+use run_workspace::RunWorkspace;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::{info, warn};
+
+use crate::cycles::tweet_cycle::safety::{self, SafetyPolicy};
+use crate::cycles::tweet_cycle::text_action;
+use crate::logger;
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+/// What the critic thinks of one draft, each out of 10.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JokeScore {
+    /// 0 to 10, would a very online person actually laugh.
+    pub funny: f32,
+    /// 0 to 10, is it clearly about this exact topic.
+    pub specific: f32,
+    /// 0 to 10, does it follow every style rule.
+    pub style: f32,
+    /// One or two sentences on what would make it funnier.
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl JokeScore {
+    pub fn total(&self) -> f32 {
+        (self.funny + self.specific + self.style) / 3.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoredDraft {
+    pub round: usize,
+    pub text: String,
+    pub score: JokeScore,
+    pub total: f32,
+}
+
+/// Everything the critic loop did, written to `critic_scores.json` in the run directory.
+#[derive(Debug, Serialize)]
+pub struct CriticReport {
+    pub topic: String,
+    pub prompt_version: String,
+    pub persona: String,
+    pub threshold: f32,
+    pub drafts: Vec<ScoredDraft>,
+    pub winner: Option<ScoredDraft>,
+    pub passed: bool,
+}
+
+/// Generate, critique, revise, as hard as `config.critic` says.
+///
+/// Each round the comedian writes `candidates` drafts. Drafts the safety gate
+/// rejects are dropped, the critic scores the rest. The best draft at or above
+/// the threshold wins. Otherwise the best draft so far and its notes go back
+/// to the comedian for another round. If no round passes, the best draft
+/// overall is used anyway so the cycle still produces something.
+pub async fn write_best_tweet(
+    models: &Models,
+    prompts: &PromptSet,
+    policy: &SafetyPolicy,
+    workspace: &RunWorkspace,
+    config: &Config,
+    topic: &str,
+    writer_prompt: &str,
+) -> Result<String> {
+    let settings = &config.critic;
+    let style_rules = prompts.render("writer", Some(topic))?;
+    let critic_preamble = prompts.render("critic", Some(topic))?;
+    let mut drafts: Vec<ScoredDraft> = Vec::new();
+    let mut winner: Option<ScoredDraft> = None;
+
+    for round in 1..=settings.max_rounds {
+        let request = match best(&drafts) {
+            None => "Create one short tweet now.".to_string(),
+            Some(previous) => format!(
+                "Your last best attempt was:\n{}\n\nA critic scored it {:.1}/10 and said: {}\n\
+                 Create one new short tweet that fixes that.",
+                previous.text, previous.total, previous.score.notes
+            ),
+        };
+
+        for _ in 0..settings.candidates {
+            let Some(text) =
+                text_action::write_styled_draft(models, &config.style, writer_prompt, &request)
+                    .await?
+            else {
+                warn!("Comedian couldn't stick to the house style, dropping the draft");
+                continue;
+            };
+            logger::comedian_log(&text);
+            if !safety::check_tweet(models, policy, workspace, &text)
+                .await?
+                .passed
+            {
+                continue;
+            }
+
+            let score =
+                match score_draft(models, &critic_preamble, &style_rules, topic, &text).await {
+                    Ok(score) => score,
+                    Err(e) => {
+                        warn!("Critic failed to score a draft, skipping it: {e}");
+                        continue;
+                    }
+                };
+            let total = score.total();
+            logger::joke_critic_log(&format!(
+                "{total:.1}/10 (funny {}, specific {}, style {})\n{}",
+                score.funny, score.specific, score.style, score.notes
+            ));
+            drafts.push(ScoredDraft {
+                round,
+                text,
+                score,
+                total,
+            });
+        }
+
+        if let Some(best) = best(&drafts).filter(|d| d.total >= settings.threshold) {
+            winner = Some(best.clone());
+            break;
+        }
+        info!(
+            "No draft reached {:.1}/10 in round {round} of {}",
+            settings.threshold, settings.max_rounds
+        );
+    }
+
+    let passed = winner.is_some();
+    let winner = winner.or_else(|| best(&drafts).cloned());
+    let report = CriticReport {
+        topic: topic.to_string(),
+        prompt_version: prompts.version.clone(),
+        persona: prompts.persona.name.clone(),
+        threshold: settings.threshold,
+        drafts,
+        winner,
+        passed,
+    };
+    save_report(models, workspace, &report)?;
+
+    let winner = report
+        .winner
+        .ok_or_else(|| anyhow!("No safe draft the critic could score for '{topic}'"))?;
+    if !passed {
+        info!(
+            "Settling for the best draft at {:.1}/10, below the {:.1} threshold",
+            winner.total, settings.threshold
+        );
+    }
+    Ok(winner.text)
+}
+
+/// Ask the critic to score one draft.
+async fn score_draft(
+    models: &Models,
+    critic_preamble: &str,
+    style_rules: &str,
+    topic: &str,
+    draft: &str,
+) -> Result<JokeScore> {
+    let request = format!(
+        "Topic: {topic}\n\nStyle rules the writer was given:\n{style_rules}\n\nDraft tweet:\n{draft}"
+    );
+    let score: JokeScore = models
+        .prompt_json(Role::Critic, critic_preamble, &request)
+        .await?;
+
+    Ok(JokeScore {
+        funny: score.funny.clamp(0.0, 10.0),
+        specific: score.specific.clamp(0.0, 10.0),
+        style: score.style.clamp(0.0, 10.0),
+        notes: score.notes,
+    })
+}
+
+fn save_report(models: &Models, workspace: &RunWorkspace, report: &CriticReport) -> Result<()> {
+    let path = workspace.file("critic_scores.json");
+    fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    let config = models.config();
+    workspace.record(
+        &path,
+        "text",
+        &[
+            ("prompt_version", &report.prompt_version),
+            ("persona", &report.persona),
+            ("comedian_model", &config.for_role(Role::Comedian).model),
+            ("critic_model", &config.for_role(Role::Critic).model),
+        ],
+    )?;
+    info!("Critic scores saved to {}", path.display());
+    Ok(())
+}
+
+fn best(drafts: &[ScoredDraft]) -> Option<&ScoredDraft> {
+    drafts.iter().max_by(|a, b| a.total.total_cmp(&b.total))
+}
@@ -240,12 +240,24 @@
 #![allow(unused_imports)]
 
 use anyhow::{anyhow, bail, Result};
+use bot_config::{Config, ImageProvider};
+use cost_meter::{Unit, Usage};
 use reqwest::Client;
+use run_workspace::RunWorkspace;
 use serde::{Deserialize, Serialize};
 use std::{fs, path::Path, time::Duration};
+use tracing::{info, info_span, warn, Instrument};
 
 use subtitle_hub;
 
+use crate::cycles::tweet_cycle::safety::{self, SafetyPolicy};
+use crate::cycles::tweet_cycle::workers;
+
+// Placeholder frames cycle through these so neighbouring slides differ.
+const PLACEHOLDER_COLORS: [&str; 6] = [
+    "0xff5f5f", "0xffd25f", "0x5fff87", "0x5fc8ff", "0xaf87ff", "0xff87d7",
+];
+
 // -----------------------------------------------------------------------------
 // Data models for FAL API requests/responses
 // -----------------------------------------------------------------------------
@@ -253,6 +265,8 @@ use subtitle_hub;
 #[derive(Serialize)]
 struct FalRequest<'a> {
     prompt: &'a str,
+    // On by default for fast-sdxl, but we rely on it so ask explicitly
+    enable_safety_checker: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -294,6 +308,14 @@ struct FalTimings {
     inference: f64,
 }
 
+/// A finished image and whether Fal's safety checker flagged it.
+#[derive(Debug)]
+pub struct FalImageResult {
+    pub base64: String,
+    pub nsfw: bool,
+    pub seed: Option<u64>,
+}
+
 // -----------------------------------------------------------------------------
 // A simple client encapsulating the Fal endpoint, key, and HTTP client
 // -----------------------------------------------------------------------------
@@ -315,18 +337,39 @@ impl FalClient {
         }
     }
 
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The endpoint without the queue host, e.g. "fal-ai/fast-sdxl", as priced
+    /// in prices.toml.
+    fn model(&self) -> &str {
+        self.endpoint
+            .trim_start_matches("https://queue.fal.run/")
+            .trim_end_matches('/')
+    }
+
     /// Main entrypoint to generate an image by prompt.
     /// 1) POST prompt -> get request ID
     /// 2) Poll until completion
-    /// 3) Return base64-encoded image data.
-    pub async fn generate_image(&self, prompt: &str) -> Result<String> {
-        let request_id = self.start_generation(prompt).await?;
-        self.poll_for_image(&request_id).await
+    /// 3) Return base64-encoded image data, and the NSFW flag.
+    pub async fn generate_image(&self, prompt: &str) -> Result<FalImageResult> {
+        let span = info_span!("image", provider = "fal", model = self.model());
+        async {
+            let request_id = self.start_generation(prompt).await?;
+            self.poll_for_image(&request_id).await
+        }
+        .instrument(span)
+        .await
+        .inspect_err(|_| pipeline_metrics::provider_error("fal"))
     }
 
     /// Step 1: Start the generation request, returning the `request_id`.
     async fn start_generation(&self, prompt: &str) -> Result<String> {
-        let req_body = FalRequest { prompt };
+        let req_body = FalRequest {
+            prompt,
+            enable_safety_checker: true,
+        };
         let resp = self
             .client
             .post(&self.endpoint)
@@ -345,8 +388,8 @@ impl FalClient {
         Ok(parsed.request_id)
     }
 
-    /// Step 2: Poll until the generation completes or fails. Return the first image's base64.
-    async fn poll_for_image(&self, request_id: &str) -> Result<String> {
+    /// Step 2: Poll until the generation completes or fails. Return the first image.
+    async fn poll_for_image(&self, request_id: &str) -> Result<FalImageResult> {
         let status_url = format!(
             "{}/requests/{}",
             self.endpoint.trim_end_matches('/'),
@@ -380,10 +423,31 @@ impl FalClient {
                     continue;
                 }
                 "SUCCESS" => {
+                    // Flagged images are billed too
+                    let model = self.model();
+                    cost_meter::record(Usage::new("fal", model, 1.0, Unit::Images));
+                    if let Some(timings) = &status_parsed.timings {
+                        cost_meter::record(Usage::new(
+                            "fal",
+                            model,
+                            timings.inference,
+                            Unit::InferenceSeconds,
+                        ));
+                    }
+
                     // Return the first base64 image
+                    let nsfw = status_parsed
+                        .has_nsfw_concepts
+                        .as_ref()
+                        .and_then(|flags| flags.first().copied())
+                        .unwrap_or(false);
                     if let Some(images) = status_parsed.images {
                         if let Some(first) = images.first() {
-                            return Ok(first.base64.clone());
+                            return Ok(FalImageResult {
+                                base64: first.base64.clone(),
+                                nsfw,
+                                seed: status_parsed.seed,
+                            });
                         }
                     }
                     bail!("No images found in SUCCESS response.");
@@ -394,42 +458,96 @@ impl FalClient {
     }
 }
 
+// -----------------------------------------------------------------------------
+// The configured image provider: Fal, or placeholders for offline runs
+// -----------------------------------------------------------------------------
+
+pub enum ImageGenerator {
+    Fal(FalClient),
+    /// Plain coloured frames from ffmpeg, never flagged and free.
+    Placeholder {
+        ffmpeg: String,
+    },
+}
+
+impl ImageGenerator {
+    /// The generator for `providers.images`, failing without a Fal key for Fal.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(match config.providers.images {
+            ImageProvider::Fal => ImageGenerator::Fal(FalClient::new(
+                &config.providers.fal_endpoint,
+                config.keys.fal()?,
+            )),
+            ImageProvider::Placeholder => ImageGenerator::Placeholder {
+                ffmpeg: config.tools.ffmpeg.clone(),
+            },
+        })
+    }
+
+    /// What's recorded as the image's endpoint in the run manifest.
+    pub fn endpoint(&self) -> &str {
+        match self {
+            ImageGenerator::Fal(client) => client.endpoint(),
+            ImageGenerator::Placeholder { .. } => "placeholder",
+        }
+    }
+
+    pub async fn generate_image(&self, prompt: &str) -> Result<FalImageResult> {
+        match self {
+            ImageGenerator::Fal(client) => client.generate_image(prompt).await,
+            ImageGenerator::Placeholder { ffmpeg } => {
+                // The same word always gets the same colour
+                let index = prompt.bytes().map(usize::from).sum::<usize>();
+                let color = PLACEHOLDER_COLORS[index % PLACEHOLDER_COLORS.len()];
+                let ffmpeg = ffmpeg.clone();
+                let png = workers::run_blocking(move || {
+                    ffmpeg_wrapper::placeholder_image(&ffmpeg, color)
+                })
+                .await??;
+                Ok(FalImageResult {
+                    base64: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png),
+                    nsfw: false,
+                    seed: None,
+                })
+            }
+        }
+    }
+}
+
 // -----------------------------------------------------------------------------
 // High-level function to create images for subtitles
 // -----------------------------------------------------------------------------
 
+/// Images Fal flags as NSFW are regenerated up to `policy.max_image_attempts`
+/// times, then left out of the slideshow.
 pub async fn create_images_for_subtitles(
-    run_id: i64,
+    workspace: &RunWorkspace,
+    images: &ImageGenerator,
     subtitles: &[subtitle_hub::SubtitleLine],
+    policy: &SafetyPolicy,
 ) -> Result<()> {
-    println!("Creating Images for Subtitles: {:?}", subtitles);
-
-    let fal_key = std::env::var("FAL_API_KEY")
-        .map_err(|_| anyhow!("FAL_API_KEY environment variable not set"))?;
-    let endpoint = "https://queue.fal.run/fal-ai/fast-sdxl";
+    info!("Creating Images for Subtitles: {:?}", subtitles);
+    let endpoint = images.endpoint();
 
-    // Create the FalClient once
-    let fal_client = FalClient::new(endpoint, fal_key);
-
-    let folder = format!("./tmp/{run_id}");
-    std::fs::create_dir_all(&folder)?;
+    std::fs::create_dir_all(workspace.dir())?;
 
     // Build a "filelist.txt" for the FFmpeg concat demuxer
     let mut filelist = String::new();
     let mut image_counter = 0;
+    let mut last_image: Option<String> = None;
 
-    println!("About To Iterate through the subtitles to make images");
+    info!("About To Iterate through the subtitles to make images");
     // For each line, generate images for each word (or partial subset).
     for (line_index, sub) in subtitles.iter().enumerate() {
         let line_duration = sub.end - sub.start;
         if line_duration <= 0.0 {
-            println!("Line Duration is under limit");
+            info!("Line Duration is under limit");
             continue;
         }
 
         let words: Vec<&str> = sub.text.split_whitespace().collect();
         if words.is_empty() {
-            println!("Word empty returning");
+            info!("Word empty returning");
             continue;
         }
 
@@ -441,15 +559,34 @@ pub async fn create_images_for_subtitles(
                 break; // Only the first word for demonstration
             }
 
-            // Generate the image
-            let b64_image = fal_client.generate_image(word).await?;
+            // Generate the image, again if Fal's safety checker flags it
+            let mut clean_image = None;
+            for attempt in 1..=policy.max_image_attempts.max(1) {
+                let image = images.generate_image(word).await?;
+                if !image.nsfw {
+                    clean_image = Some(image);
+                    break;
+                }
+                safety::record_flagged_image(workspace, word, attempt)?;
+            }
+            let Some(image) = clean_image else {
+                warn!("Dropping the image for '{word}', every attempt was flagged NSFW");
+                continue;
+            };
 
             // Decode and write to disk
             let decoded =
-                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &b64_image)?;
-            let filename_base = format!("word_{line_index}_{image_counter}.png");
-            let filename = format!("{folder}/{filename_base}");
+                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &image.base64)?;
+            let filename_base = RunWorkspace::image_name(line_index, image_counter);
+            let filename = workspace.image(line_index, image_counter);
             std::fs::write(&filename, decoded)?;
+            let seed = image.seed.map(|s| s.to_string()).unwrap_or_default();
+            workspace.record(
+                &filename,
+                "images",
+                &[("endpoint", endpoint), ("prompt", word), ("seed", &seed)],
+            )?;
+            pipeline_metrics::image_generated();
 
             // Append info to the filelist
             filelist.push_str(&format!(
@@ -458,22 +595,25 @@ pub async fn create_images_for_subtitles(
                 filename_base, word_duration
             ));
 
+            last_image = Some(filename_base);
             image_counter += 1;
         }
     }
 
     // FFmpeg concat demuxer requires repeating the last image without a duration.
-    if image_counter > 0 {
-        let last_image = format!("word_{}_{}.png", subtitles.len() - 1, image_counter - 1);
+    // Track it by name, lines can be skipped or have their image dropped.
+    if let Some(last_image) = last_image {
         filelist.push_str(&format!("file '{}'\n", last_image));
     }
 
     // Write out the filelist
-    let filelist_path = format!("{folder}/filelist.txt");
+    let filelist_path = workspace.filelist();
     std::fs::write(&filelist_path, &filelist)?;
-    println!(
+    workspace.record(&filelist_path, "images", &[])?;
+    info!(
         "Finished writing filelist.txt with {} images at {}",
-        image_counter, filelist_path
+        image_counter,
+        filelist_path.display()
     );
 
     Ok(())
@@ -3,12 +3,34 @@
 
 use agent_twitter_client::scraper::Scraper;
 use anyhow::{anyhow, Result};
+use bot_config::Config;
 use rig::{completion::Prompt, providers};
-use std::{fs, path::Path, time::SystemTime};
-
-use crate::consts;
-use crate::cycles::tweet_cycle::{audio, image_action, srt, text_action};
-use twitter_scraper;
+use std::{
+    fmt, fs,
+    future::Future,
+    path::Path,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime},
+};
+
+use crate::cycles::tweet_cycle::image_action::{self, ImageGenerator};
+use crate::cycles::tweet_cycle::safety::{self, SafetyPolicy};
+use crate::cycles::tweet_cycle::workers::{self, WorkerPools};
+use crate::cycles::tweet_cycle::{audio, srt, text_action};
+use content_source::ContentSource;
+use cost_meter::{PriceTable, UsageMeter};
+use openai_wrapper::{Models, Role};
+use post_scheduler::{Clip, Shutdown};
+use prompt_library::{PromptLibrary, PromptSet};
+use publisher::{PostMetadata, PostRequest, PostResult, Publisher, UnreadableVideo};
+use run_history::{CostRecord, RunHistory, RunSource, RunStatus, RunTweet};
+use run_workspace::{RunWorkspace, Storage};
+use source_registry::{SourceCandidate, SourceRegistry};
+use tracing::{error, info, info_span, warn, Instrument, Span};
+use twitter_scraper::{self, SeenTweetStore, TwitterSession};
 
 // -------------------------------------------------------
 // -- Loop States ---
@@ -18,30 +40,32 @@ pub struct Initial;
 
 pub struct FetchTweets {
     pub topic: String,
-    pub user_id: String,
+    pub source: SourceCandidate,
+    pub prompts: PromptSet,
 }
 
 pub struct GenerateTweetText {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
+    pub prompts: PromptSet,
 }
 
 pub struct GenerateAudio {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
     pub tweet_text: String,
 }
 
 pub struct GenerateSubtitles {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
     pub tweet_text: String,
     pub audio_path: String,
 }
 
 pub struct GenerateImages {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
     pub tweet_text: String,
     pub audio_path: String,
     pub srt_file: String,
@@ -49,7 +73,7 @@ pub struct GenerateImages {
 
 pub struct ImagesGenerated {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
     pub tweet_text: String,
     pub audio_path: String,
     pub srt_file: String,
@@ -57,83 +81,193 @@ pub struct ImagesGenerated {
 
 pub struct ImagesCombinedIntoVideoPlusAudio {
     pub topic: String,
-    pub user_id: String,
+    pub source: String,
     pub tweet_text: String,
     pub audio_path: String,
     pub srt_file: String,
+    pub video_path: String,
 }
 
-pub struct Complete;
-
 // -------------------------------------------------------
 // A generic TweetCycle that holds a particular state T
 // -------------------------------------------------------
 
 pub struct TweetCycle<T> {
-    run_id: i64,
+    workspace: RunWorkspace,
     state: T,
 }
 
+impl<T> TweetCycle<T> {
+    pub fn run_id(&self) -> i64 {
+        self.workspace.run_id()
+    }
+
+    pub fn workspace(&self) -> &RunWorkspace {
+        &self.workspace
+    }
+}
+
 impl TweetCycle<Initial> {
-    /// Start a new TweetCycle in the initial state.
-    pub fn new(run_id: i64) -> Self {
-        println!("Starting tweet cycle at {}", run_id);
+    /// Start a new TweetCycle in the initial state, writing into `workspace`.
+    pub fn new(workspace: RunWorkspace) -> Self {
+        info!("Starting tweet cycle at {}", workspace.run_id());
         TweetCycle {
-            run_id,
+            workspace,
             state: Initial,
         }
     }
 
-    /// 1. Choose a random user in a random section.
-    pub async fn select_user(self) -> Result<TweetCycle<FetchTweets>> {
-        let (topic, user_id) = twitter_scraper::get_random_user_id()
-            .ok_or_else(|| anyhow!("No valid user_id found in any section."))?;
-        println!("Randomly chose section '{topic}' with user_id '{user_id}'.");
+    /// 1. Choose the next source (account, search, list, trends, home timeline,
+    ///    RSS, subreddit or Hacker News), weighted and skipping recently used ones.
+    ///    X feeds are left out when we aren't logged in.
+    ///    The prompt version and persona for the cycle are picked here too.
+    pub async fn select_source(
+        self,
+        sources: &mut SourceRegistry,
+        prompts: &mut PromptLibrary,
+        twitter_available: bool,
+    ) -> Result<TweetCycle<FetchTweets>> {
+        let source = sources
+            .select_matching(|c| twitter_available || !c.feed.needs_twitter())
+            .ok_or_else(|| anyhow!("No valid source found in any section."))?;
+        let prompts = prompts.next_prompts(&source.section);
+        info!(
+            "Chose section '{}' with {}, prompts {} as {}.",
+            source.section, source.feed, prompts.version, prompts.persona.name
+        );
         Ok(TweetCycle {
-            run_id: self.run_id,
+            workspace: self.workspace,
             state: FetchTweets {
-                topic: topic.to_string(),
-                user_id: user_id.to_string(),
+                topic: source.section.clone(),
+                source,
+                prompts,
             },
         })
     }
 }
 
 impl TweetCycle<FetchTweets> {
-    /// 2. Fetch tweets & summarize => topic
+    /// 2. Fetch tweets (or posts) from the content source & summarize => topic
+    ///
+    /// Only tweets we haven't summarized before are used. Returns `None` when
+    /// there is nothing new or the summarizer found no topic in it, so the
+    /// caller can skip the rest of the cycle. The summary is saved as `topic.json`
+    /// and the prompt version and persona as `prompts.json`. The new tweets go
+    /// into the run history, marked if the topic came from them.
     pub async fn generate_topic(
         self,
-        scraper: &mut Scraper,
-        openai_client: &providers::openai::Client,
-    ) -> Result<TweetCycle<GenerateTweetText>> {
-        let tweets_text = twitter_scraper::fetch_tweets(scraper, &self.state.user_id).await?;
-        let topic = text_action::summarize_tweets_and_get_topic(openai_client, tweets_text).await?;
-        println!("Initial topic for {} user: {}", self.state.topic, topic);
+        content: &mut dyn ContentSource,
+        models: &Models,
+        sources: &mut SourceRegistry,
+        seen: &mut SeenTweetStore,
+        history: &RunHistory,
+    ) -> Result<Option<TweetCycle<GenerateTweetText>>> {
+        info!("Fetching from {}", content.name());
+        let tweets = content.fetch().await?;
+        let newest_id = twitter_scraper::newest_tweet_id(&tweets);
+        sources.record_visit(&self.state.source.key, newest_id.as_deref());
+
+        let fetched = tweets.len();
+        let unseen = seen.filter_unseen(tweets);
+        info!("{} of {fetched} fetched tweets are new.", unseen.len());
+        if unseen.is_empty() {
+            return Ok(None);
+        }
 
-        Ok(TweetCycle {
-            run_id: self.run_id,
+        let summary =
+            text_action::summarize_tweets_and_get_topic(models, &self.state.prompts, &unseen)
+                .await?;
+        // Either way these tweets are used up, don't summarize them again next cycle
+        seen.mark_seen(&unseen)?;
+        let source_ids = summary
+            .as_ref()
+            .map(|s| s.source_tweet_ids.as_slice())
+            .unwrap_or_default();
+        let run_tweets: Vec<RunTweet> = unseen
+            .iter()
+            .map(|t| RunTweet {
+                tweet_id: t.id.clone(),
+                author: t.author.clone(),
+                text: t.text.clone(),
+                url: t.url.clone(),
+                used_for_topic: source_ids.contains(&t.id),
+            })
+            .collect();
+        history.record_tweets(self.run_id(), &run_tweets)?;
+        let Some(summary) = summary else {
+            return Ok(None);
+        };
+        info!(
+            "Initial topic for {} section: {}",
+            self.state.topic, summary.topic
+        );
+
+        let stamp = self.state.prompts.stamp();
+        let summarizer_model = &models.config().for_role(Role::Summarizer).model;
+        let topic_path = self.workspace.file("topic.json");
+        fs::write(&topic_path, serde_json::to_string_pretty(&summary)?)?;
+        self.workspace.record(
+            &topic_path,
+            "topic",
+            &[
+                ("source", &self.state.source.key),
+                ("prompt_version", &stamp.version),
+                ("model", summarizer_model),
+            ],
+        )?;
+        let prompts_path = self.workspace.file("prompts.json");
+        fs::write(&prompts_path, serde_json::to_string_pretty(&stamp)?)?;
+        self.workspace.record(&prompts_path, "topic", &[])?;
+        let topic = summary.topic;
+
+        Ok(Some(TweetCycle {
+            workspace: self.workspace,
             state: GenerateTweetText {
                 topic,
-                user_id: self.state.user_id,
+                source: self.state.source.feed.to_string(),
+                prompts: self.state.prompts,
             },
-        })
+        }))
     }
 }
 
 impl TweetCycle<GenerateTweetText> {
-    /// 3. Generate the tweet text
+    /// 2b. Run the topic past the safety gate before anyone jokes about it.
+    ///
+    /// Returns `None` for blocked topics (blocklist, moderator, real-person
+    /// tragedies) so the caller skips the cycle. Reasons go to `safety.jsonl`.
+    pub async fn screen_topic(
+        self,
+        models: &Models,
+        policy: &SafetyPolicy,
+    ) -> Result<Option<TweetCycle<GenerateTweetText>>> {
+        let check = safety::check_topic(models, policy, &self.workspace, &self.state.topic).await?;
+        Ok(check.passed.then_some(self))
+    }
+
+    /// 3. Generate the tweet text, scored and revised by the joke critic
     pub async fn generate_tweet_text(
         self,
-        openai_client: &providers::openai::Client,
+        models: &Models,
+        policy: &SafetyPolicy,
+        config: &Config,
     ) -> Result<TweetCycle<GenerateAudio>> {
-        let tweet_text = text_action::generate_tweet_text(openai_client, &self.state.topic).await?;
-        println!("\n--- TWEET OUT ---\n{tweet_text}\n");
+        let tweet_text = text_action::generate_tweet_text(
+            models,
+            &self.state.prompts,
+            policy,
+            &self.workspace,
+            config,
+            &self.state.topic,
+        )
+        .await?;
+        info!(tweet = %tweet_text, "Tweet out");
 
         Ok(TweetCycle {
-            run_id: self.run_id,
+            workspace: self.workspace,
             state: GenerateAudio {
                 topic: self.state.topic,
-                user_id: self.state.user_id,
+                source: self.state.source,
                 tweet_text,
             },
         })
@@ -141,17 +275,17 @@ impl TweetCycle<GenerateTweetText> {
 }
 
 impl TweetCycle<GenerateAudio> {
-    pub async fn generate_audio(self) -> Result<TweetCycle<GenerateSubtitles>> {
+    pub async fn generate_audio(self, config: &Config) -> Result<TweetCycle<GenerateSubtitles>> {
         let audio_path =
-            audio::generate_audio(&self.run_id.to_string(), &self.state.tweet_text).await?;
+            audio::generate_audio(&self.workspace, config, &self.state.tweet_text).await?;
 
-        println!("Audio path: {audio_path}\n");
+        info!("Audio path: {audio_path}");
 
         Ok(TweetCycle {
-            run_id: self.run_id,
+            workspace: self.workspace,
             state: GenerateSubtitles {
                 topic: self.state.topic,
-                user_id: self.state.user_id,
+                source: self.state.source,
                 tweet_text: self.state.tweet_text,
                 audio_path,
             },
@@ -161,19 +295,25 @@ impl TweetCycle<GenerateAudio> {
 
 // This should be generating audio!
 impl TweetCycle<GenerateSubtitles> {
-    /// 5. Generate SRT file from audio
-    pub async fn generate_subtitles(self) -> Result<TweetCycle<GenerateImages>> {
-        let (srt_file, ass_file) =
-            srt::generate_subtitles(&self.run_id.to_string(), &self.state.audio_path)?;
+    /// 5. Generate SRT file from audio, with whisperx off the runtime
+    pub async fn generate_subtitles(self, config: &Config) -> Result<TweetCycle<GenerateImages>> {
+        let workspace = self.workspace.clone();
+        let audio_path = self.state.audio_path.clone();
+        let tweet_text = self.state.tweet_text.clone();
+        let config = config.clone();
+        let (srt_file, ass_file) = workers::run_blocking(move || {
+            srt::generate_subtitles(&workspace, &config, &audio_path, &tweet_text)
+        })
+        .await??;
 
-        println!("SRT file: {:?}", srt_file);
-        println!("Ass file: {:?}", ass_file);
+        info!("SRT file: {:?}", srt_file);
+        info!("Ass file: {:?}", ass_file);
 
         Ok(TweetCycle {
-            run_id: self.run_id,
+            workspace: self.workspace,
             state: GenerateImages {
                 topic: self.state.topic,
-                user_id: self.state.user_id,
+                source: self.state.source,
                 tweet_text: self.state.tweet_text,
                 audio_path: self.state.audio_path,
                 srt_file,
@@ -185,16 +325,22 @@ impl TweetCycle<GenerateSubtitles> {
 impl TweetCycle<GenerateImages> {
     // We should seperate out parse srt
     /// 5. Parse the SRT & create images for each word
-    pub async fn generate_images(self) -> Result<TweetCycle<ImagesGenerated>> {
+    pub async fn generate_images(
+        self,
+        config: &Config,
+        policy: &SafetyPolicy,
+    ) -> Result<TweetCycle<ImagesGenerated>> {
         let subtitles = subtitle_hub::parse_srt(&self.state.srt_file)?;
-        println!("Parsed {} subtitle lines.", subtitles.len());
+        info!("Parsed {} subtitle lines.", subtitles.len());
 
-        image_action::create_images_for_subtitles(self.run_id, &subtitles).await?;
+        let images = ImageGenerator::from_config(config)?;
+        image_action::create_images_for_subtitles(&self.workspace, &images, &subtitles, policy)
+            .await?;
         Ok(TweetCycle {
-            run_id: self.run_id,
+            workspace: self.workspace,
             state: ImagesGenerated {
                 topic: self.state.topic,
-                user_id: self.state.user_id,
+                source: self.state.source,
                 tweet_text: self.state.tweet_text,
                 audio_path: self.state.audio_path,
                 srt_file: self.state.srt_file,
@@ -204,94 +350,461 @@ impl TweetCycle<GenerateImages> {
 }
 
 impl TweetCycle<ImagesGenerated> {
-    /// 6. (Optional) Build an MP4 slideshow, post final MP4, etc.
-    /// For demonstration, we skip to “Complete.”
-    pub fn create_video_from_images(self) -> TweetCycle<ImagesCombinedIntoVideoPlusAudio> {
-        let _result = ffmpeg_wrapper::create_video_from_filelist_and_audio(
-            self.run_id,
-            &self.state.audio_path,
-        );
-        // tweet_center::post_tweet_with_video(...);
+    /// 6. Render the images, the audio and the subtitles to the final MP4.
+    pub async fn create_video_from_images(
+        self,
+        config: &Config,
+    ) -> Result<TweetCycle<ImagesCombinedIntoVideoPlusAudio>> {
+        let workspace = self.workspace.clone();
+        let audio_path = self.state.audio_path.clone();
+        let ffmpeg = config.tools.ffmpeg.clone();
+        let rendered = workers::run_blocking(move || {
+            ffmpeg_wrapper::create_video_from_filelist_and_audio(&workspace, &audio_path, &ffmpeg)
+        })
+        .await?;
+        let video_path = rendered.map_err(|e| anyhow!("Failed to create video: {e}"))?;
 
-        TweetCycle {
-            run_id: self.run_id,
+        Ok(TweetCycle {
+            workspace: self.workspace,
             state: ImagesCombinedIntoVideoPlusAudio {
                 topic: self.state.topic,
-                user_id: self.state.user_id,
+                source: self.state.source,
                 tweet_text: self.state.tweet_text,
                 audio_path: self.state.audio_path,
                 srt_file: self.state.srt_file,
+                video_path,
             },
-        }
+        })
     }
 }
 
 impl TweetCycle<ImagesCombinedIntoVideoPlusAudio> {
-    /// 6. (Optional) Build an MP4 slideshow, post final MP4, etc.
-    /// For demonstration, we skip to “Complete.”
-    pub fn complete(self) -> TweetCycle<Complete> {
-        println!("Slideshow or final video generation can go here...");
-        //ffmpeg_wrapper::create_video_from_filelist_and_audio(&self.state.audio_path)?;
-        //tweet_center::post_tweet_with_video(...);
+    /// 7. Hand the final MP4 to the scheduler, which posts it at the next slot.
+    pub fn into_clip(self) -> Clip {
+        Clip {
+            run_id: self.workspace.run_id(),
+            video_path: self.state.video_path,
+            caption: self.state.tweet_text,
+            title: self.state.topic,
+            rendered_at: chrono::Utc::now().timestamp(),
+            failed_slots: 0,
+        }
+    }
+}
 
-        TweetCycle {
-            run_id: self.run_id,
-            state: Complete,
+/// 8. Post a queued clip to `publishers`, the platforms the schedule has room on.
+///
+/// A failing platform is logged and skipped, it doesn't fail the post. The
+/// publish stage and the posts go into the clip's run in the history.
+pub async fn publish_clip(
+    ctx: &CycleContext,
+    clip: &Clip,
+    publishers: &[&dyn Publisher],
+) -> Result<Published> {
+    let run_id = clip.run_id;
+    let published = stage(ctx, run_id, "publish", async {
+        Ok(publish(clip, publishers).await)
+    })
+    .instrument(info_span!("run", run_id))
+    .await?;
+    for post in &published.posts {
+        ctx.history
+            .record_post(run_id, &post.platform, &post.post_id, post.url.as_deref())?;
+    }
+    Ok(published)
+}
+
+/// What posting a clip got done.
+#[derive(Debug, Default)]
+pub struct Published {
+    pub posts: Vec<PostResult>,
+    /// Why the video couldn't be read, if a platform said so. No slot will fix that.
+    pub unreadable_video: Option<String>,
+}
+
+async fn publish(clip: &Clip, publishers: &[&dyn Publisher]) -> Published {
+    let mut published = Published::default();
+    if publishers.is_empty() {
+        info!("No publishers to post to, skipping upload.");
+        return published;
+    }
+    let request = PostRequest {
+        video_path: clip.video_path.clone(),
+        caption: clip.caption.clone(),
+        metadata: PostMetadata {
+            title: Some(clip.title.clone()),
+            ..Default::default()
+        },
+    };
+
+    for publisher in publishers {
+        match publisher.publish(&request).await {
+            Ok(post) => {
+                info!(
+                    "Published to {}: {} {}",
+                    post.platform,
+                    post.post_id,
+                    post.url.as_deref().unwrap_or("")
+                );
+                pipeline_metrics::video_posted(&post.platform);
+                published.posts.push(post);
+            }
+            Err(e) => {
+                warn!("Failed to publish to {}: {e}", publisher.platform());
+                pipeline_metrics::provider_error(publisher.platform());
+                if let Some(unreadable) = e.downcast_ref::<UnreadableVideo>() {
+                    published.unreadable_video = Some(unreadable.to_string());
+                    // The others would fail on the same file
+                    break;
+                }
+            }
         }
     }
+    published
 }
 
 // -------------------------------------------------------
 // Example: tying it all together in a run function
 // -------------------------------------------------------
 
-pub async fn run_tweet_cycle(
-    scraper: &mut Scraper,
-    openai_client: &providers::openai::Client,
-) -> Result<()> {
-    //let run_id = 1740592533;
-    //
-    //let sub = subtitle_hub::SubtitleLine {
-    //    start: 1.0,
-    //    end: 2.0,
-    //    text: "noice orca".to_string(),
-    //};
-    //
-    //let subtitles = vec![sub];
-    //image_action::create_images_for_subtitles(run_id, &subtitles).await?;
-    ////
-    ////
-    ////let audio_path = format!("./tmp/{}/tts.mp3", run_id);
-    ////let _result = ffmpeg_wrapper::create_video_from_filelist_and_audio(run_id, &audio_path);
-    //return Ok(());
-
-    //let run_id = 1739943571;
-    //let audio_path = "./tmp/1739943571/tts.mp3";
-    //let srt_file = srt::generate_subtitles(&run_id.to_string(), audio_path)?;
-    //println!("SRT file: {:?}", srt_file);
-    //
-    //return Ok(())https://twitter.com/lz_web3;
-    let run_id = chrono::Local::now().timestamp();
-    // let run_id = 1739864879;
-
-    // We can skip all these steps
-    let _final_cycle = TweetCycle::new(run_id)
-        .select_user()
-        .await?
-        .generate_topic(scraper, openai_client)
-        .await?
-        .generate_tweet_text(openai_client)
-        .await?
-        .generate_audio()
-        .await?
-        .generate_subtitles()
-        .await?
-        .generate_images()
-        .await?
-        .create_video_from_images();
-
-    // If needed, you can keep `_final_cycle` to do something with the final state
-    // e.g., return data or finalize the tweet with video, etc.
+/// The clients and settings every cycle uses, set up once in `main`.
+pub struct CycleContext {
+    pub models: Models,
+    pub publishers: Vec<Box<dyn Publisher>>,
+    pub safety: SafetyPolicy,
+    pub storage: Storage,
+    pub history: RunHistory,
+    pub prices: PriceTable,
+    pub workers: WorkerPools,
+    pub config: Config,
+}
+
+/// How a cycle ended, for the run history. An error instead is a failed run.
+enum CycleOutcome {
+    Completed(Clip),
+    Skipped(String),
+}
+
+/// Where the source, topic and safety stages leave a run.
+enum Intake {
+    Ready(Box<TweetCycle<GenerateTweetText>>),
+    Skipped(String),
+}
+
+/// A run past the safety gate, waiting for [`finish_tweet_cycle`].
+pub struct StartedRun {
+    cycle: TweetCycle<GenerateTweetText>,
+    meter: UsageMeter,
+    span: Span,
+}
+
+impl StartedRun {
+    pub fn run_id(&self) -> i64 {
+        self.cycle.run_id()
+    }
+}
+
+/// Context on errors out of [`stage`], so the cycle knows which stage failed.
+#[derive(Debug)]
+struct FailedStage(String);
+
+impl fmt::Display for FailedStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} stage failed", self.0)
+    }
+}
+
+/// The stage an error came out of, "cycle" if it wasn't one.
+fn failed_stage(error: &anyhow::Error) -> &str {
+    error
+        .downcast_ref::<FailedStage>()
+        .map_or("cycle", |stage| stage.0.as_str())
+}
+
+/// Run one stage in its own `stage` span, recording when it started, how long
+/// it took and whether it failed. Stages with a worker pool wait for a worker
+/// first, the wait isn't counted.
+async fn stage<T>(
+    ctx: &CycleContext,
+    run_id: i64,
+    name: &str,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let span = info_span!("stage", run_id, stage = name);
+    let _worker = ctx.workers.acquire(name).instrument(span.clone()).await;
+    let started_at = chrono::Utc::now();
+    let started = Instant::now();
+    let result = work.instrument(span.clone()).await;
+    let elapsed = started.elapsed();
+    let error = result.as_ref().err().map(|e| e.to_string());
+
+    let _stage = span.enter();
+    let duration_ms = elapsed.as_millis() as u64;
+    match &error {
+        None => info!(duration_ms, "Stage finished"),
+        Some(e) => error!(duration_ms, error = %e, "Stage failed"),
+    }
+    pipeline_metrics::stage_finished(name, elapsed.as_secs_f64(), error.is_none());
+    // Losing a timing isn't worth losing the run over
+    if let Err(e) = ctx
+        .history
+        .record_stage(run_id, name, started_at, elapsed, error.as_deref())
+    {
+        warn!("Failed to record stage {name} of run {run_id}: {e}");
+    }
+    result.map_err(|e| e.context(FailedStage(name.to_string())))
+}
+
+/// Start a run and take it through the source, topic and safety stages, one
+/// run at a time. `None` if it was skipped or failed there. A second shutdown
+/// signal stops it where it is, recording it as failed.
+pub async fn start_tweet_cycle(
+    ctx: &CycleContext,
+    twitter: Option<&mut TwitterSession>,
+    sources: &mut SourceRegistry,
+    prompts: &mut PromptLibrary,
+    seen: &mut SeenTweetStore,
+    shutdown: &Shutdown,
+) -> Result<Option<StartedRun>> {
+    let run_id = next_run_id();
+
+    // Pick up edits to the source registry without a restart
+    match sources.reload_if_changed() {
+        Ok(true) => {
+            info!("Reloaded source registry from {}", sources.path().display());
+            if let Some(session) = twitter.as_deref() {
+                // Feeds of handles that don't resolve fail in their own runs
+                if let Err(e) = twitter_scraper::resolve_handles(session.scraper(), sources).await {
+                    warn!("Failed to resolve the reloaded registry's X handles: {e}");
+                }
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Keeping previous source registry: {e}"),
+    }
+
+    let workspace = ctx.storage.create_run(run_id)?;
+    ctx.history.start_run(run_id, workspace.dir())?;
+    pipeline_metrics::cycle_started();
+
+    let span = info_span!("run", run_id);
+    let meter = UsageMeter::new();
+    let stages = meter
+        .scope(intake_stages(
+            ctx, workspace, twitter, sources, prompts, seen,
+        ))
+        .instrument(span.clone());
+    let outcome = tokio::select! {
+        intake = stages => intake,
+        _ = shutdown.forced() => Err(anyhow!("Interrupted by shutdown")),
+    };
+
+    let outcome = match outcome {
+        Ok(Intake::Ready(cycle)) => {
+            return Ok(Some(StartedRun {
+                cycle: *cycle,
+                meter,
+                span,
+            }))
+        }
+        Ok(Intake::Skipped(reason)) => Ok(CycleOutcome::Skipped(reason)),
+        Err(e) => Err(e),
+    };
+    finish_run(ctx, run_id, &meter, &span, outcome)?;
+    Ok(None)
+}
+
+/// Run ids are start times in seconds, bumped past the last one so runs
+/// started within a second of each other don't share a directory.
+fn next_run_id() -> i64 {
+    static LAST_RUN_ID: AtomicI64 = AtomicI64::new(0);
+    let now = chrono::Local::now().timestamp();
+    let last = LAST_RUN_ID
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+            Some(now.max(last + 1))
+        })
+        .unwrap_or_default();
+    now.max(last + 1)
+}
 
+/// Take a started run through text, audio, subtitles, images and render, to a
+/// clip for the scheduler's queue. `None` if it failed, see [`finish_run`]. Runs concurrently with
+/// other runs, sharing the stage worker pools.
+pub async fn finish_tweet_cycle(
+    ctx: Arc<CycleContext>,
+    run: StartedRun,
+    shutdown: Shutdown,
+) -> Result<Option<Clip>> {
+    let StartedRun { cycle, meter, span } = run;
+    let run_id = cycle.run_id();
+    let stages = meter
+        .scope(production_stages(&ctx, cycle))
+        .instrument(span.clone());
+    let outcome = tokio::select! {
+        outcome = stages => outcome,
+        _ = shutdown.forced() => Err(anyhow!("Interrupted by shutdown")),
+    };
+    finish_run(&ctx, run_id, &meter, &span, outcome)
+}
+
+/// Record costs and how the run ended, handing back a completed run's clip.
+/// A run's error is recorded as its failure and not handed back, so one run
+/// can't stop the others; only failing to record it is an error.
+fn finish_run(
+    ctx: &CycleContext,
+    run_id: i64,
+    meter: &UsageMeter,
+    span: &Span,
+    outcome: Result<CycleOutcome>,
+) -> Result<Option<Clip>> {
+    let _run = span.enter();
+    record_costs(ctx, run_id, meter)?;
+    let now = chrono::Local::now().timestamp();
+    let (status, detail) = match &outcome {
+        Ok(CycleOutcome::Completed(_)) => {
+            pipeline_metrics::cycle_completed(now);
+            (RunStatus::Completed, None)
+        }
+        Ok(CycleOutcome::Skipped(reason)) => {
+            info!("{reason}, skipping cycle {run_id}.");
+            pipeline_metrics::cycle_skipped(now);
+            (RunStatus::Skipped, Some(reason.clone()))
+        }
+        Err(e) => {
+            error!("Run {run_id} failed: {e:#}");
+            pipeline_metrics::cycle_failed(failed_stage(e), now);
+            (RunStatus::Failed, Some(format!("{e:#}")))
+        }
+    };
+    ctx.history.finish_run(run_id, status, detail.as_deref())?;
+
+    Ok(match outcome {
+        Ok(CycleOutcome::Completed(clip)) => Some(clip),
+        _ => None,
+    })
+}
+
+/// Price what the run used and store it in the run history, failed runs included.
+fn record_costs(ctx: &CycleContext, run_id: i64, meter: &UsageMeter) -> Result<()> {
+    let mut total = 0.0;
+    for usage in meter.take() {
+        let cost_usd = ctx.prices.cost(&usage);
+        total += cost_usd;
+        ctx.history.record_cost(
+            run_id,
+            &CostRecord {
+                provider: usage.provider,
+                item: usage.item,
+                quantity: usage.quantity,
+                unit: usage.unit.to_string(),
+                cost_usd,
+            },
+        )?;
+    }
+    info!("Run {run_id} cost an estimated ${total:.4}");
+    pipeline_metrics::spent(total);
     Ok(())
 }
+
+/// The stages that share the source registry, prompts and seen tweets.
+async fn intake_stages(
+    ctx: &CycleContext,
+    workspace: RunWorkspace,
+    mut twitter: Option<&mut TwitterSession>,
+    sources: &mut SourceRegistry,
+    prompts: &mut PromptLibrary,
+    seen: &mut SeenTweetStore,
+) -> Result<Intake> {
+    let models = &ctx.models;
+    let history = &ctx.history;
+    let run_id = workspace.run_id();
+
+    let twitter_available = twitter.is_some();
+    let cycle = stage(
+        ctx,
+        run_id,
+        "source",
+        TweetCycle::new(workspace).select_source(sources, prompts, twitter_available),
+    )
+    .await?;
+    history.set_source(
+        run_id,
+        &RunSource {
+            section: cycle.state.source.section.clone(),
+            source: cycle.state.source.feed.to_string(),
+            source_key: cycle.state.source.key.clone(),
+            prompt_version: cycle.state.prompts.version.clone(),
+            persona: cycle.state.prompts.persona.name.clone(),
+        },
+    )?;
+    let mut content = content_source::source_for_feed(
+        &cycle.state.source.feed,
+        twitter.take(),
+        ctx.config.paths.content_fixtures.as_deref(),
+    )?;
+
+    let Some(cycle) = stage(
+        ctx,
+        run_id,
+        "topic",
+        cycle.generate_topic(content.as_mut(), models, sources, seen, history),
+    )
+    .await?
+    else {
+        return Ok(Intake::Skipped(
+            "No new tweets or no topic in them".to_string(),
+        ));
+    };
+    history.set_topic(run_id, &cycle.state.topic)?;
+
+    let Some(cycle) = stage(
+        ctx,
+        run_id,
+        "safety",
+        cycle.screen_topic(models, &ctx.safety),
+    )
+    .await?
+    else {
+        return Ok(Intake::Skipped(
+            "Topic blocked by the safety gate".to_string(),
+        ));
+    };
+
+    Ok(Intake::Ready(Box::new(cycle)))
+}
+
+/// The stages after the safety gate, each with its own worker pool.
+async fn production_stages(
+    ctx: &CycleContext,
+    cycle: TweetCycle<GenerateTweetText>,
+) -> Result<CycleOutcome> {
+    let safety = &ctx.safety;
+    let history = &ctx.history;
+    let run_id = cycle.run_id();
+
+    let config = &ctx.config;
+    let cycle = stage(
+        ctx,
+        run_id,
+        "text",
+        cycle.generate_tweet_text(&ctx.models, safety, config),
+    )
+    .await?;
+    history.set_tweet_text(run_id, &cycle.state.tweet_text)?;
+
+    let cycle = stage(ctx, run_id, "audio", cycle.generate_audio(config)).await?;
+    let cycle = stage(ctx, run_id, "subtitles", cycle.generate_subtitles(config)).await?;
+    let cycle = stage(ctx, run_id, "images", cycle.generate_images(config, safety)).await?;
+
+    let clip = stage(
+        ctx,
+        run_id,
+        "render",
+        cycle.create_video_from_images(config),
+    )
+    .await?
+    .into_clip();
+    history.set_video_path(run_id, &clip.video_path)?;
+
+    Ok(CycleOutcome::Completed(clip))
+}
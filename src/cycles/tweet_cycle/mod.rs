@@ -1,5 +1,8 @@
 pub mod audio;
+pub mod critic;
 pub mod image_action;
 pub mod loops;
+pub mod safety;
 pub mod srt;
 pub mod text_action;
+pub mod workers;
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use openai_wrapper::{Models, Role};
+use regex::RegexBuilder;
+use run_workspace::RunWorkspace;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{fs, io::Write, path::Path};
+
+use crate::logger;
+
+const MODERATOR_PREAMBLE: &str = "You moderate content for a comedy account that posts short \
+videos. You decide whether a topic or a finished tweet is safe to post. Reject sexual content, \
+hate or harassment aimed at a group or a private person, self-harm, graphic violence, and \
+anything that makes fun of a real, identifiable person's death, injury, illness, or a disaster \
+or attack with real victims. Jokes about public figures' public actions, products, companies \
+and the news in general are fine.";
+
+// -----------------------------------------------------------------------------
+// Data models
+// -----------------------------------------------------------------------------
+
+/// `safety.toml`: what the safety stage rejects.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SafetyPolicy {
+    /// Words and phrases that reject a topic or tweet outright.
+    /// Matched case-insensitively on whole words.
+    pub blocklist: Vec<String>,
+    /// Ask the `moderator` role from models.toml about every topic and tweet.
+    pub moderation: bool,
+    /// How many times an image Fal flags as NSFW is regenerated before we drop it.
+    pub max_image_attempts: usize,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            blocklist: Vec::new(),
+            moderation: true,
+            max_image_attempts: 3,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    /// A missing file means the default policy: moderation on, no blocklist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(SafetyPolicy::default());
+        }
+        let raw = fs::read_to_string(path)?;
+        toml::from_str(&raw).map_err(|e| anyhow!("Failed to parse {}: {e}", path.display()))
+    }
+
+    /// The first blocklisted word or phrase in `text`.
+    pub fn blocked_term(&self, text: &str) -> Option<&str> {
+        self.blocklist
+            .iter()
+            .filter(|term| !term.trim().is_empty())
+            .find(|term| {
+                let pattern = format!(r"\b{}\b", regex::escape(term.trim()));
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|re| re.is_match(text))
+                    .unwrap_or(false)
+            })
+            .map(String::as_str)
+    }
+}
+
+/// The moderator's answer about one topic or tweet.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModerationVerdict {
+    /// True if this is fine to post.
+    pub allowed: bool,
+    /// True if it is about the death, injury or suffering of real, identifiable people.
+    #[serde(default)]
+    pub real_person_tragedy: bool,
+    /// Short names of what is wrong, e.g. "sexual", "hate", "tragedy". Empty if allowed.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// One sentence on why.
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// One decision of the safety stage, appended to `safety.jsonl` in the run directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct SafetyCheck {
+    /// "topic", "tweet" or "image".
+    pub stage: String,
+    pub subject: String,
+    pub passed: bool,
+    pub reason: Option<String>,
+}
+
+impl SafetyCheck {
+    fn passed(stage: &str, subject: &str) -> Self {
+        SafetyCheck {
+            stage: stage.to_string(),
+            subject: subject.to_string(),
+            passed: true,
+            reason: None,
+        }
+    }
+
+    fn rejected(stage: &str, subject: &str, reason: impl Into<String>) -> Self {
+        SafetyCheck {
+            stage: stage.to_string(),
+            subject: subject.to_string(),
+            passed: false,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Checks
+// -----------------------------------------------------------------------------
+
+/// Topics are checked before we write a joke about them. Real-person tragedies
+/// are rejected here even if the moderator otherwise allows the topic.
+pub async fn check_topic(
+    models: &Models,
+    policy: &SafetyPolicy,
+    workspace: &RunWorkspace,
+    topic: &str,
+) -> Result<SafetyCheck> {
+    let check = match policy.blocked_term(topic) {
+        Some(term) => SafetyCheck::rejected("topic", topic, format!("blocklisted term '{term}'")),
+        None if policy.moderation => {
+            let request = format!(
+                "Topic the comedian is about to joke about:\n{topic}\n\n\
+                 Is it okay to make a joke video about this?"
+            );
+            match moderate(models, &request).await {
+                Ok(verdict) if verdict.real_person_tragedy => SafetyCheck::rejected(
+                    "topic",
+                    topic,
+                    format!("real-person tragedy: {}", verdict.reason),
+                ),
+                Ok(verdict) => from_verdict("topic", topic, verdict),
+                // Fail closed, a skipped cycle is cheap
+                Err(e) => SafetyCheck::rejected("topic", topic, format!("moderator failed: {e}")),
+            }
+        }
+        None => SafetyCheck::passed("topic", topic),
+    };
+    record(workspace, &check)?;
+    Ok(check)
+}
+
+/// Every comedian draft is checked before the critic sees it.
+pub async fn check_tweet(
+    models: &Models,
+    policy: &SafetyPolicy,
+    workspace: &RunWorkspace,
+    tweet: &str,
+) -> Result<SafetyCheck> {
+    let check = match policy.blocked_term(tweet) {
+        Some(term) => SafetyCheck::rejected("tweet", tweet, format!("blocklisted term '{term}'")),
+        None if policy.moderation => {
+            let request = format!("Tweet about to be posted:\n{tweet}\n\nIs it okay to post?");
+            match moderate(models, &request).await {
+                Ok(verdict) => from_verdict("tweet", tweet, verdict),
+                Err(e) => SafetyCheck::rejected("tweet", tweet, format!("moderator failed: {e}")),
+            }
+        }
+        None => SafetyCheck::passed("tweet", tweet),
+    };
+    record(workspace, &check)?;
+    Ok(check)
+}
+
+/// Write down an image Fal's safety checker flagged as NSFW.
+pub fn record_flagged_image(workspace: &RunWorkspace, prompt: &str, attempt: usize) -> Result<()> {
+    record(
+        workspace,
+        &SafetyCheck::rejected(
+            "image",
+            prompt,
+            format!("Fal flagged attempt {attempt} as NSFW"),
+        ),
+    )
+}
+
+async fn moderate(models: &Models, request: &str) -> Result<ModerationVerdict> {
+    models
+        .prompt_json(Role::Moderator, MODERATOR_PREAMBLE, request)
+        .await
+}
+
+fn from_verdict(stage: &str, subject: &str, verdict: ModerationVerdict) -> SafetyCheck {
+    if verdict.allowed {
+        SafetyCheck::passed(stage, subject)
+    } else {
+        let reason = if verdict.categories.is_empty() {
+            verdict.reason
+        } else {
+            format!("{}: {}", verdict.categories.join(", "), verdict.reason)
+        };
+        SafetyCheck::rejected(stage, subject, reason)
+    }
+}
+
+/// Append to `safety.jsonl` in the run directory, and print rejections.
+fn record(workspace: &RunWorkspace, check: &SafetyCheck) -> Result<()> {
+    if let Some(reason) = &check.reason {
+        logger::safety_log(&format!(
+            "Rejected {} '{}': {reason}",
+            check.stage, check.subject
+        ));
+    }
+
+    let path = workspace.file("safety.jsonl");
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(check)?)?;
+    workspace.record(&path, "safety", &[])?;
+    Ok(())
+}
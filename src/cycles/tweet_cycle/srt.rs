@@ -1,31 +1,56 @@
 use agent_twitter_client::scraper::Scraper;
 use anyhow::{anyhow, Result};
+use bot_config::{Config, TranscriptionProvider};
 use rig::{completion::Prompt, providers};
+use run_workspace::RunWorkspace;
 use std::{fs, path::Path};
+use tracing::{info, warn};
 
-pub fn generate_subtitles(id: &str, audio_path: &str) -> Result<(String, String)> {
-    println!("Starting transcription");
-    subtitle_hub::run_docker_transcription(id, audio_path)?;
-    println!("Finished transcription");
-    let srt_file = format!("./tmp/{}/{}.srt", id, "tts");
-    println!("Using SRT File: {}", srt_file);
-    let srt_output_path = subtitle_hub::convert(id, &srt_file)?;
-    println!("Finished converting Subtitles");
-    let ass_path = format!("./tmp/{}/tts.ass", id);
-    let ass_subtitles = convert_srt_to_ass(&srt_output_path, &ass_path);
+/// Transcribe the audio with the configured provider. The offline one times
+/// the words of `tweet_text` evenly over the audio's length instead.
+pub fn generate_subtitles(
+    workspace: &RunWorkspace,
+    config: &Config,
+    audio_path: &str,
+    tweet_text: &str,
+) -> Result<(String, String)> {
+    let tools = &config.tools;
+    info!("Starting transcription");
+    match config.providers.transcription {
+        TranscriptionProvider::Whisperx => subtitle_hub::run_docker_transcription(
+            workspace,
+            audio_path,
+            &tools.docker,
+            &tools.whisperx_image,
+        )?,
+        TranscriptionProvider::Offline => {
+            let seconds = ffmpeg_wrapper::probe_duration(&tools.ffprobe, audio_path)?;
+            subtitle_hub::write_offline_transcription(workspace, tweet_text, seconds)?
+        }
+    }
+    info!("Finished transcription");
+    let srt_file = workspace.transcription_srt().to_string_lossy().to_string();
+    info!("Using SRT File: {}", srt_file);
+    let srt_output_path = subtitle_hub::convert(workspace, &srt_file)?;
+    info!("Finished converting Subtitles");
+    let ass_path = workspace.subtitles_ass().to_string_lossy().to_string();
+    let ass_subtitles = convert_srt_to_ass(&tools.ffmpeg, &srt_output_path, &ass_path);
+    if Path::new(&ass_subtitles).exists() {
+        workspace.record(&ass_subtitles, "subtitles", &[("source", &srt_output_path)])?;
+    }
     Ok((srt_output_path, ass_subtitles))
 }
 
-fn convert_srt_to_ass(srt_path: &str, ass_path: &str) -> String {
-    let status = std::process::Command::new("ffmpeg")
+fn convert_srt_to_ass(ffmpeg: &str, srt_path: &str, ass_path: &str) -> String {
+    let status = std::process::Command::new(ffmpeg)
         .args(&["-i", srt_path, ass_path])
         .status()
         .expect("Failed to execute ffmpeg");
 
     if status.success() {
-        println!("Successfully converted {} to {}", srt_path, ass_path);
+        info!("Successfully converted {} to {}", srt_path, ass_path);
     } else {
-        eprintln!("Failed to convert {} to {}", srt_path, ass_path);
+        warn!("Failed to convert {} to {}", srt_path, ass_path);
     }
 
     ass_path.to_string()
@@ -1,44 +1,468 @@
 use anyhow::Result;
-use rig::{completion::Prompt, providers};
+use bot_config::{Config, Style};
+use openai_wrapper::{Models, Role};
+use prompt_library::PromptSet;
+use run_workspace::RunWorkspace;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{info, warn};
+use twitter_scraper::SourceTweet;
 
-use crate::consts;
+use crate::cycles::tweet_cycle::critic;
+use crate::cycles::tweet_cycle::safety::SafetyPolicy;
 use crate::logger;
 
+// Enough to see what the feed is on about without drowning the prompt.
+const MAX_SUMMARY_TWEETS: usize = 20;
+
+// X's limit for a tweet without Premium.
+const X_MAX_CHARS: usize = 280;
+// Roughly how fast the ElevenLabs voices read, ~150 words a minute.
+const TTS_WORDS_PER_SECOND: f32 = 2.5;
+// How many times we send a draft back to the comedian for breaking the style rules.
+const MAX_STYLE_RETRIES: usize = 2;
+// Below this the summarizer is guessing, and the video would be about nothing.
+const MIN_TOPIC_CONFIDENCE: f32 = 0.3;
+
+// -----------------------------------------------------------------------------
+// Structured LLM outputs
+// -----------------------------------------------------------------------------
+
+/// What the summarizer found in a batch of tweets.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TopicSummary {
+    /// Short, specific topic, no more than 5 words. Empty if the tweets share no real story.
+    pub topic: String,
+    /// Exact phrases copied from the tweets that the topic is built on.
+    #[serde(default)]
+    pub key_quotes: Vec<String>,
+    /// The `id` of every tweet the topic comes from.
+    #[serde(default)]
+    pub source_tweet_ids: Vec<String>,
+    /// 0.0 to 1.0, how clearly the tweets point at this one topic.
+    pub confidence: f32,
+}
+
+/// One draft from the comedian.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TweetDraft {
+    /// The tweet, exactly as it should be posted.
+    pub tweet: String,
+    /// One short line on what the joke is going for.
+    #[serde(default)]
+    pub angle: String,
+}
+
+/// Numbered, most engaged first, with reply and quote context inlined.
+pub fn format_tweets_for_summary(tweets: &[SourceTweet]) -> String {
+    twitter_scraper::rank_by_engagement(tweets.to_vec())
+        .iter()
+        .take(MAX_SUMMARY_TWEETS)
+        .enumerate()
+        .map(|(i, tweet)| format!("[{}] id {} · {}", i + 1, tweet.id, tweet.to_prompt_block()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Ask the summarizer for a topic.
+/// Returns `None` when it found no topic, or isn't confident enough in the one it found.
 pub async fn summarize_tweets_and_get_topic(
-    client: &providers::openai::Client,
-    tweets: String,
-) -> Result<String> {
-    let agent = client
-        .agent(consts::DEFAULT_PROMPT_MODEL)
-        .preamble(consts::TWEET_SUMMARIZER_PROMPT)
-        .build();
-    let response = agent.prompt(&tweets).await?;
-
-    for line in response.lines() {
-        if let Some(stripped) = line.strip_prefix("Topic:") {
-            return Ok(stripped.trim().to_string());
-        }
+    models: &Models,
+    prompts: &PromptSet,
+    tweets: &[SourceTweet],
+) -> Result<Option<TopicSummary>> {
+    let preamble = prompts.render("summarizer", None)?;
+    let context = format_tweets_for_summary(tweets);
+    let mut summary: TopicSummary = models
+        .prompt_json(Role::Summarizer, &preamble, &context)
+        .await?;
+    logger::summarizer_log(&format!(
+        "{} (confidence {:.2})\nquotes: {:?}",
+        summary.topic, summary.confidence, summary.key_quotes
+    ));
+
+    summary.topic = summary.topic.trim().to_string();
+    if summary.topic.is_empty() {
+        info!("Summarizer found no topic in {} tweets", tweets.len());
+        return Ok(None);
+    }
+    if summary.confidence < MIN_TOPIC_CONFIDENCE {
+        info!(
+            "Summarizer is only {:.2} sure about '{}', below {MIN_TOPIC_CONFIDENCE}",
+            summary.confidence, summary.topic
+        );
+        return Ok(None);
     }
-    Ok("NoTopicFound".to_string())
+
+    // Drop ids the model made up
+    let known: HashSet<&str> = tweets.iter().map(|t| t.id.as_str()).collect();
+    summary
+        .source_tweet_ids
+        .retain(|id| known.contains(id.as_str()));
+    if summary.source_tweet_ids.is_empty() {
+        warn!(
+            "Summarizer didn't cite any real tweet ids for '{}'",
+            summary.topic
+        );
+    }
+
+    Ok(Some(summary))
 }
 
+/// Merge the writer prompt with the topic, then let the comedian and critic
+/// go back and forth until a draft is good enough (see `critic::write_best_tweet`).
 pub async fn generate_tweet_text(
-    openai_client: &providers::openai::Client,
+    models: &Models,
+    prompts: &PromptSet,
+    safety: &SafetyPolicy,
+    workspace: &RunWorkspace,
+    config: &Config,
     topic: &str,
 ) -> Result<String> {
-    let initial_prompt = openai_wrapper::generate_prompt_with_topic(
-        openai_client,
-        consts::TWEET_WRITER_PROMPT,
+    let writer_prompt = prompts.render("writer", Some(topic))?;
+    let merger_preamble = prompts.render("prompt_merger", Some(topic))?;
+    let initial_prompt =
+        openai_wrapper::generate_prompt_with_topic(models, &merger_preamble, &writer_prompt, topic)
+            .await?;
+    logger::system_log(&format!("Initial tweet-writer prompt:\n{initial_prompt}"));
+
+    critic::write_best_tweet(
+        models,
+        prompts,
+        safety,
+        workspace,
+        config,
         topic,
+        &initial_prompt,
     )
-    .await?;
-    logger::system_log(&format!("Initial tweet-writer prompt:\n{initial_prompt}"));
+    .await
+}
+
+// -----------------------------------------------------------------------------
+// House style: the rules from TWEET_WRITER_PROMPT, enforced instead of hoped for
+// -----------------------------------------------------------------------------
 
-    let tweet_agent = openai_client
-        .agent(consts::DEFAULT_PROMPT_MODEL)
-        .preamble(&initial_prompt)
-        .build();
+/// The result of running a draft through `enforce_house_style`.
+/// `text` has every fixable rule fixed; `problems` are what couldn't be fixed.
+#[derive(Debug, Clone)]
+pub struct StyleCheck {
+    pub text: String,
+    pub fixes: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+impl StyleCheck {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check and normalise a draft.
+///
+/// Fixed in place: wrapping quotes, emojis, hashtags, capital letters and `!`.
+/// Not fixable (reported in `problems`): mentioning tweeting, going over the X
+/// character limit, or taking longer than `max_spoken_seconds` to read out.
+pub fn enforce_house_style(raw: &str, max_spoken_seconds: f32) -> StyleCheck {
+    let mut fixes = Vec::new();
+    let mut problems = Vec::new();
+
+    let mut text = raw.trim().to_string();
+    if let Some(unquoted) = strip_wrapping_quotes(&text) {
+        text = unquoted;
+        fixes.push("removed wrapping quotes".to_string());
+    }
+
+    let without_emojis: String = text.chars().filter(|c| !is_emoji(*c)).collect();
+    if without_emojis != text {
+        text = without_emojis;
+        fixes.push("removed emojis".to_string());
+    }
+
+    let without_hashtags = remove_hashtags(&text);
+    if without_hashtags != text {
+        text = without_hashtags;
+        fixes.push("removed hashtags".to_string());
+    }
+
+    let lowercase = text.to_lowercase();
+    if lowercase != text {
+        text = lowercase;
+        fixes.push("lowercased".to_string());
+    }
+
+    let without_bangs = remove_exclamations(&text);
+    if without_bangs != text {
+        text = without_bangs;
+        fixes.push("removed exclamation marks".to_string());
+    }
+
+    text = tidy_whitespace(&text);
+
+    if text.is_empty() {
+        problems.push("the tweet is empty".to_string());
+    }
+    if mentions_tweeting(&text) {
+        problems.push("it mentions tweeting or twitter".to_string());
+    }
+    let chars = text.chars().count();
+    if chars > X_MAX_CHARS {
+        problems.push(format!(
+            "it is {chars} characters, the limit is {X_MAX_CHARS}"
+        ));
+    }
+    let seconds = estimate_spoken_seconds(&text);
+    if seconds > max_spoken_seconds {
+        problems.push(format!(
+            "it takes about {seconds:.0}s to read out, the limit is {max_spoken_seconds:.0}s"
+        ));
+    }
 
-    let tweet_text = tweet_agent.prompt("Create one short tweet now.").await?;
-    Ok(tweet_text)
+    StyleCheck {
+        text,
+        fixes,
+        problems,
+    }
+}
+
+/// How long the TTS voice will take to read `text`, in seconds.
+pub fn estimate_spoken_seconds(text: &str) -> f32 {
+    text.split_whitespace().count() as f32 / TTS_WORDS_PER_SECOND
+}
+
+/// Ask the comedian for a draft and run it through the house style.
+/// Drafts that can't be fixed go back with the list of broken rules.
+/// Returns `None` if the comedian never produced a usable draft.
+pub async fn write_styled_draft(
+    models: &Models,
+    style: &Style,
+    writer_prompt: &str,
+    request: &str,
+) -> Result<Option<String>> {
+    let mut request = request.to_string();
+
+    for attempt in 0..=MAX_STYLE_RETRIES {
+        let draft: TweetDraft = models
+            .prompt_json(Role::Comedian, writer_prompt, &request)
+            .await?;
+        let check = enforce_house_style(&draft.tweet, style.max_spoken_seconds);
+        if !check.fixes.is_empty() {
+            logger::system_log(&format!("Style fixes: {}", check.fixes.join(", ")));
+        }
+        if check.is_ok() {
+            return Ok(Some(check.text));
+        }
+
+        info!(
+            "Draft broke the house style ({}), attempt {} of {}",
+            check.problems.join("; "),
+            attempt + 1,
+            MAX_STYLE_RETRIES + 1
+        );
+        request = format!(
+            "This draft broke the rules:\n{}\n\nProblems: {}.\n\
+             Write a new short tweet that keeps the joke and fixes those problems.",
+            draft.tweet,
+            check.problems.join("; ")
+        );
+    }
+    Ok(None)
+}
+
+fn strip_wrapping_quotes(text: &str) -> Option<String> {
+    for (open, close) in [('"', '"'), ('“', '”'), ('\'', '\'')] {
+        if let Some(inner) = text.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+            // Only if the quotes wrap the whole thing, not `"a" and "b"`
+            if !inner.contains(open) && !inner.contains(close) {
+                return Some(inner.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF   // emoticons, pictographs, transport, flags, symbols
+        | 0x2600..=0x27BF   // misc symbols and dingbats
+        | 0x2B00..=0x2BFF   // arrows and stars like ⭐
+        | 0x2300..=0x23FF   // technical symbols like ⌛
+        | 0xFE00..=0xFE0F   // variation selectors
+        | 0x200D            // zero width joiner
+        | 0x20E3            // keycap
+        | 0xE0020..=0xE007F // tag characters
+    )
+}
+
+/// Trailing hashtag clusters are dropped, inline ones keep their word: "love #rust" -> "love rust".
+fn remove_hashtags(text: &str) -> String {
+    let is_hashtag = |word: &str| {
+        word.strip_prefix('#')
+            .is_some_and(|tag| tag.chars().next().is_some_and(char::is_alphanumeric))
+    };
+
+    text.lines()
+        .map(|line| {
+            let mut words: Vec<&str> = line.split(' ').collect();
+            let kept = words
+                .iter()
+                .rposition(|w| !is_hashtag(w) && !w.is_empty())
+                .map_or(0, |last| last + 1);
+            if words[kept..].iter().any(|w| is_hashtag(w)) {
+                words.truncate(kept);
+            }
+            words
+                .into_iter()
+                .map(|w| if is_hashtag(w) { &w[1..] } else { w })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `!` becomes `.`, and disappears after other punctuation: "wow!!" -> "wow.", "what?!" -> "what?".
+fn remove_exclamations(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '!' || c == '¡' {
+            if !out.ends_with(['.', '?', '!']) && !out.trim_end().is_empty() {
+                out.push('.');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Collapse runs of spaces and keep at most one blank line between lines.
+fn tidy_whitespace(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    let mut out: Vec<String> = Vec::new();
+    for line in lines {
+        if line.is_empty() && out.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+fn mentions_tweeting(text: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric() && c != '.')
+        .map(|w| w.trim_matches('.'))
+        .any(|w| {
+            matches!(
+                w,
+                "tweet" | "tweets" | "tweeting" | "tweeted" | "retweet" | "retweets" | "twitter"
+            ) || w == "x.com"
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: f32 = 30.0;
+
+    #[test]
+    fn fixes_what_it_can() {
+        let cases = [
+            (
+                "\"my cat is a landlord\"",
+                "my cat is a landlord",
+                &["removed wrapping quotes"][..],
+            ),
+            (
+                "“my cat is a landlord”",
+                "my cat is a landlord",
+                &["removed wrapping quotes"],
+            ),
+            ("\"a\" and \"b\"", "\"a\" and \"b\"", &[]),
+            (
+                "my cat is a landlord 😂🏠",
+                "my cat is a landlord",
+                &["removed emojis"],
+            ),
+            (
+                "love #rust and go #coding #dev",
+                "love rust and go",
+                &["removed hashtags"],
+            ),
+            ("my cat writes c# now", "my cat writes c# now", &[]),
+            (
+                "Big News About The Cat",
+                "big news about the cat",
+                &["lowercased"],
+            ),
+            ("wow!! what?!", "wow. what?", &["removed exclamation marks"]),
+            (
+                "  too   many\n\n\n\nblank lines  ",
+                "too many\n\nblank lines",
+                &[],
+            ),
+            (
+                "\"HUGE news!! #breaking 🚨\"",
+                "huge news.",
+                &[
+                    "removed wrapping quotes",
+                    "removed emojis",
+                    "removed hashtags",
+                    "lowercased",
+                    "removed exclamation marks",
+                ],
+            ),
+        ];
+        for (raw, text, fixes) in cases {
+            let check = enforce_house_style(raw, LIMIT);
+            assert_eq!(check.text, text, "text of {raw:?}");
+            assert_eq!(check.fixes, fixes, "fixes of {raw:?}");
+            assert!(check.is_ok(), "{raw:?} has problems: {:?}", check.problems);
+        }
+    }
+
+    #[test]
+    fn reports_what_it_cannot_fix() {
+        let long = "a".repeat(X_MAX_CHARS + 1);
+        // 80 words is 32s at the voice's pace, in 160 characters
+        let wordy = "a ".repeat(80);
+        let cases = [
+            ("", LIMIT, Some("the tweet is empty")),
+            ("🙂", LIMIT, Some("the tweet is empty")),
+            (
+                "i just tweeted about my cat",
+                LIMIT,
+                Some("mentions tweeting"),
+            ),
+            ("my cat has a twitter", LIMIT, Some("mentions tweeting")),
+            (
+                "my cat lives on x.com now",
+                LIMIT,
+                Some("mentions tweeting"),
+            ),
+            ("my cat sweetly retired", LIMIT, None),
+            (long.as_str(), LIMIT, Some("characters, the limit is 280")),
+            (wordy.as_str(), LIMIT, Some("to read out, the limit is 30s")),
+            (wordy.as_str(), 60.0, None),
+        ];
+        for (raw, max_seconds, problem) in cases {
+            let check = enforce_house_style(raw, max_seconds);
+            match problem {
+                Some(problem) => assert!(
+                    check.problems.iter().any(|p| p.contains(problem)),
+                    "expected '{problem}' for {raw:?}, got {:?}",
+                    check.problems
+                ),
+                None => assert!(check.is_ok(), "{raw:?} has problems: {:?}", check.problems),
+            }
+        }
+    }
 }
@@ -0,0 +1,57 @@
+use anyhow::{anyhow, Result};
+use bot_config::Workers;
+use std::collections::HashMap;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::Span;
+
+/// Bounded worker pools for the stages after the safety gate, shared by every
+/// run in flight: while one run waits on Fal another can be in ElevenLabs.
+///
+/// `workers.runs` are in flight at once. The source, topic and safety stages
+/// stay one run at a time, they share the source registry. Transcription and
+/// render are CPU bound (whisperx, ffmpeg), the rest wait on a provider.
+pub struct WorkerPools {
+    max_runs: usize,
+    pools: HashMap<&'static str, Semaphore>,
+}
+
+impl WorkerPools {
+    pub fn new(workers: &Workers) -> Self {
+        // A pool of 0 would never run, `Config::validate` rejects it
+        let pools = [
+            ("text", workers.text),
+            ("audio", workers.tts),
+            ("subtitles", workers.transcription),
+            ("images", workers.images),
+            ("render", workers.render),
+        ]
+        .into_iter()
+        .map(|(stage, size)| (stage, Semaphore::new(size.max(1))))
+        .collect();
+        WorkerPools {
+            max_runs: workers.runs.max(1),
+            pools,
+        }
+    }
+
+    pub fn max_runs(&self) -> usize {
+        self.max_runs
+    }
+
+    /// Wait for a worker of `stage`'s pool, `None` for stages without one.
+    pub async fn acquire(&self, stage: &str) -> Option<SemaphorePermit<'_>> {
+        // The semaphores are never closed
+        self.pools.get(stage)?.acquire().await.ok()
+    }
+}
+
+/// Run blocking work (ffmpeg, the whisperx container) on tokio's blocking
+/// threads so it doesn't stall the other runs, keeping the caller's span.
+pub async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T> {
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(work))
+        .await
+        .map_err(|e| anyhow!("Blocking task failed: {e}"))
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, bail, Result};
+use bot_config::{Config, ImageProvider, LlmReplay, TranscriptionProvider, TtsProvider};
+use cost_meter::PriceTable;
+use openai_wrapper::{ModelConfig, Models};
+use post_scheduler::Shutdown;
+use prompt_library::PromptLibrary;
+use run_history::RunHistory;
+use run_workspace::Storage;
+use source_registry::SourceRegistry;
+use std::{fs, path::Path, sync::Arc};
+use tracing::info;
+use twitter_scraper::SeenTweetStore;
+
+use crate::cycles::tweet_cycle::{
+    loops::{self, CycleContext},
+    safety::SafetyPolicy,
+    workers::WorkerPools,
+};
+
+// Saved tweets, their sources file and the canned LLM responses.
+const FIXTURES_DIR: &str = "fixtures/dry_run";
+// Same persona and prompt picks on every dry run.
+const PROMPT_SEED: u64 = 42;
+
+/// `juicero dry-run`: one run through every stage with nothing external, from
+/// the saved tweets in `fixtures/dry_run` through canned LLM responses, a tone
+/// for the voice, evenly timed subtitles and placeholder images to a real
+/// ffmpeg render. Needs only ffmpeg and ffprobe, no keys, docker or network.
+/// With `providers.llm_replay` set to "replay" the LLM answers come from the
+/// cassette in `paths.llm_fixtures` instead, and "record" makes one.
+///
+/// Runs go to `dry_run` in the data root, with their own history, and are
+/// never posted. Fails unless the run ends in a video ffprobe can read.
+pub async fn run(mut config: Config) -> Result<()> {
+    let fixtures = Path::new(FIXTURES_DIR);
+    config.providers.tts = TtsProvider::Offline;
+    config.providers.transcription = TranscriptionProvider::Offline;
+    config.providers.images = ImageProvider::Placeholder;
+    // Canned responses, unless `providers.llm_replay` records or replays a cassette
+    if matches!(
+        config.providers.llm_replay,
+        LlmReplay::Off | LlmReplay::Canned
+    ) {
+        config.providers.llm_replay = LlmReplay::Canned;
+        config.paths.llm_fixtures = fixtures.join("llm");
+    }
+    config.paths.data_root = config.paths.data_root.join("dry_run");
+    config.paths.sources = fixtures.join("sources.toml");
+    config.paths.seen_tweets = config.paths.data_root.join("seen_tweets.json");
+    config.validate()?;
+
+    let paths = &config.paths;
+    let storage = Storage::new(&paths.data_root, crate::retention(&config))?;
+    let history = RunHistory::open(storage.root().join("runs.sqlite"))?;
+    let mut sources = SourceRegistry::load(&paths.sources)?;
+    // The same saved tweets every time, so none of them may count as seen
+    if paths.seen_tweets.exists() {
+        fs::remove_file(&paths.seen_tweets)?;
+    }
+    let mut seen = SeenTweetStore::load(&paths.seen_tweets)?;
+    let models = Models::with_cassette(
+        ModelConfig::load(&paths.models)?,
+        &crate::llm_keys(&config),
+        crate::cassette(&config),
+    )?;
+    let mut prompts = PromptLibrary::load(&paths.prompts)?.with_seed(PROMPT_SEED);
+    let safety = SafetyPolicy::load(&paths.safety)?;
+    let prices = PriceTable::load(&paths.prices)?;
+    let workers = WorkerPools::new(&config.workers);
+    let shutdown = Shutdown::listen()?;
+
+    info!("Dry run into {}", storage.root().display());
+    let ffprobe = config.tools.ffprobe.clone();
+    let ctx = Arc::new(CycleContext {
+        models,
+        publishers: Vec::new(),
+        safety,
+        storage,
+        history,
+        prices,
+        workers,
+        config,
+    });
+
+    let started =
+        loops::start_tweet_cycle(&ctx, None, &mut sources, &mut prompts, &mut seen, &shutdown)
+            .await?;
+    let clip = match started {
+        Some(run) => loops::finish_tweet_cycle(ctx.clone(), run, shutdown).await?,
+        None => None,
+    };
+    let Some(clip) = clip else {
+        let run = ctx
+            .history
+            .runs(1, None)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("The dry run didn't start"))?;
+        bail!(
+            "Dry run {} ended {}: {}",
+            run.run_id,
+            run.status,
+            run.status_detail.as_deref().unwrap_or("no video")
+        );
+    };
+
+    let seconds = ffmpeg_wrapper::probe_duration(&ffprobe, &clip.video_path)?;
+    println!(
+        "Dry run {} rendered {} ({seconds:.1}s): {}",
+        clip.run_id, clip.video_path, clip.caption
+    );
+    Ok(())
+}
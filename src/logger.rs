@@ -1,47 +1,160 @@
 #![allow(dead_code)]
 use owo_colors::OwoColorize;
+use std::fmt;
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// Target of the agent events below. The pretty console shows them as banners,
+/// JSON output logs them like everything else.
+pub const AGENT_TARGET: &str = "agent";
 
 const BAR_LENGTH: usize = 120;
 
-fn print_bordered(agent_line: impl std::fmt::Display, output_line: impl std::fmt::Display) {
-    let bar = "═".repeat(BAR_LENGTH);
-    println!("\n{}", agent_line);
-    println!("\n{}", output_line);
-    println!("{}", bar);
+/// Who is talking, which decides the banner title and colour.
+#[derive(Debug, Clone, Copy)]
+enum Agent {
+    Summarizer,
+    Comedian,
+    PromptWriter,
+    JokeCritic,
+    Safety,
+    System,
+}
+
+impl Agent {
+    const ALL: [Agent; 6] = [
+        Agent::Summarizer,
+        Agent::Comedian,
+        Agent::PromptWriter,
+        Agent::JokeCritic,
+        Agent::Safety,
+        Agent::System,
+    ];
+
+    /// The `agent` field of the event.
+    fn name(&self) -> &'static str {
+        match self {
+            Agent::Summarizer => "summarizer",
+            Agent::Comedian => "comedian",
+            Agent::PromptWriter => "prompt_writer",
+            Agent::JokeCritic => "joke_critic",
+            Agent::Safety => "safety",
+            Agent::System => "system",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Agent> {
+        Agent::ALL.into_iter().find(|agent| agent.name() == name)
+    }
 }
 
 pub fn summarizer_log(output: &str) {
-    print_bordered(
-        "Summarizer Agent".red().bold().underline(),
-        output.red().bold(),
-    );
+    agent_event(Agent::Summarizer, output);
 }
 
 pub fn comedian_log(output: &str) {
-    print_bordered(
-        "Comedian Agent".yellow().bold().underline(),
-        output.yellow().bold(),
-    );
+    agent_event(Agent::Comedian, output);
 }
 
 pub fn prompt_writer_log(output: &str) {
-    print_bordered(
-        "Prompt Writer Agent".bright_blue().bold().underline(),
-        output.bright_blue().bold(),
-    );
+    agent_event(Agent::PromptWriter, output);
 }
 
 pub fn joke_critic_log(output: &str) {
-    print_bordered(
-        "Joke Critic Writer Agent"
-            .bright_magenta()
-            .bold()
-            .underline(),
-        output.bright_magenta().bold(),
-    );
+    agent_event(Agent::JokeCritic, output);
+}
+
+pub fn safety_log(output: &str) {
+    agent_event(Agent::Safety, output);
 }
 
 pub fn system_log(output: &str) {
-    println!("{}", "System Log".white().bold().underline());
-    println!("\n{}", output.blue());
+    agent_event(Agent::System, output);
+}
+
+fn agent_event(agent: Agent, output: &str) {
+    tracing::info!(target: AGENT_TARGET, agent = agent.name(), "{output}");
+}
+
+// -----------------------------------------------------------------------------
+// Pretty console layer
+// -----------------------------------------------------------------------------
+
+/// Prints agent events as the coloured, bordered banners. The plain console
+/// layer skips them, see `telemetry::init`.
+pub struct BannerLayer;
+
+impl<S: Subscriber> Layer<S> for BannerLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != AGENT_TARGET {
+            return;
+        }
+        let mut fields = BannerFields::default();
+        event.record(&mut fields);
+        if let Some(agent) = fields.agent.as_deref().and_then(Agent::from_name) {
+            print_banner(agent, &fields.message);
+        }
+    }
+}
+
+#[derive(Default)]
+struct BannerFields {
+    agent: Option<String>,
+    message: String,
+}
+
+impl Visit for BannerFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "agent" {
+            self.agent = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
+}
+
+fn print_banner(agent: Agent, output: &str) {
+    match agent {
+        Agent::Summarizer => print_bordered(
+            "Summarizer Agent".red().bold().underline(),
+            output.red().bold(),
+        ),
+        Agent::Comedian => print_bordered(
+            "Comedian Agent".yellow().bold().underline(),
+            output.yellow().bold(),
+        ),
+        Agent::PromptWriter => print_bordered(
+            "Prompt Writer Agent".bright_blue().bold().underline(),
+            output.bright_blue().bold(),
+        ),
+        Agent::JokeCritic => print_bordered(
+            "Joke Critic Writer Agent"
+                .bright_magenta()
+                .bold()
+                .underline(),
+            output.bright_magenta().bold(),
+        ),
+        Agent::Safety => print_bordered(
+            "Safety Gate".bright_red().bold().underline(),
+            output.bright_red().bold(),
+        ),
+        Agent::System => {
+            println!("{}", "System Log".white().bold().underline());
+            println!("\n{}", output.blue());
+        }
+    }
+}
+
+fn print_bordered(agent_line: impl std::fmt::Display, output_line: impl std::fmt::Display) {
+    let bar = "═".repeat(BAR_LENGTH);
+    println!("\n{}", agent_line);
+    println!("\n{}", output_line);
+    println!("{}", bar);
 }
@@ -1,32 +1,347 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use bot_config::{Config, LlmReplay};
+use cost_meter::{Budget, PriceTable};
 use dotenv::dotenv;
-use rig::providers;
-use std::env;
-use tokio::time::Duration;
+use openai_wrapper::{Cassette, Models, ProviderKeys, ReplayMode};
+use post_scheduler::{ClipQueue, Schedule, Shutdown};
+use prompt_library::PromptLibrary;
+use publisher::Accounts;
+use run_history::{RunHistory, RunStatus};
+use run_workspace::{Retention, Storage};
+use source_registry::SourceRegistry;
+use std::{env, sync::Arc};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use twitter_scraper::{SeenTweetStore, TwitterCredentials, TwitterSession};
+
+use cycles::tweet_cycle::{loops::CycleContext, safety::SafetyPolicy, workers::WorkerPools};
+use scheduler::Scheduler;
 
-mod consts;
 mod cycles;
+mod dry_run;
 mod logger;
-mod tweet_center;
+mod scheduler;
+mod telemetry;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
+    let (config, args) = Config::load(env::args().skip(1).collect())?;
+    telemetry::init(config.telemetry.log_format)?;
+
+    let command = args.first().map(String::as_str);
+    // These two leave the data root alone
+    match command {
+        Some("check") => {
+            config.validate()?;
+            println!("Config OK: {config:#?}");
+            return Ok(());
+        }
+        Some("dry-run") => return dry_run::run(config).await,
+        _ => {}
+    }
+
+    let paths = &config.paths;
+    let storage = Storage::new(&paths.data_root, retention(&config))?;
+    let history = RunHistory::open(paths.run_history())?;
+    match command {
+        None => {}
+        Some("gc") => {
+            let queue = ClipQueue::load(paths.clip_queue())?;
+            return gc(&storage, &queue, args.iter().any(|a| a == "--dry-run"));
+        }
+        Some("runs") => return runs(&history, &args[1..]),
+        Some(other) => anyhow::bail!(
+            "Unknown command '{other}', expected no command, 'gc [--dry-run]', \
+             'runs [<run_id>] [--limit N] [--status S]', 'check' or 'dry-run'"
+        ),
+    }
+    // Fail on a missing key or tool now, not an hour into the first run
+    config.validate()?;
+
+    // Without X we can still run off RSS, reddit and Hacker News sections
+    info!("Creating Twitter Scrapper");
+    let login = match twitter_credentials(&config) {
+        Ok(credentials) => TwitterSession::start(credentials, &paths.twitter_cookies).await,
+        Err(e) => Err(e),
+    };
+    // One session for the feeds and for posting, so a re-login on one side serves both
+    let twitter = match login {
+        Ok(session) => Some(Arc::new(Mutex::new(session))),
+        Err(e) => {
+            warn!("Twitter login failed, only using non-X sources: {e}");
+            None
+        }
+    };
+
+    info!("Loading Source Registry from {}", paths.sources.display());
+    let mut sources = SourceRegistry::load(&paths.sources)?;
+    if let Some(session) = &twitter {
+        twitter_scraper::resolve_handles(session.lock().await.scraper(), &mut sources).await?;
+    }
+
+    let mut seen = SeenTweetStore::load(&paths.seen_tweets)?;
+
+    info!("Creating LLM Clients");
+    let models = Models::load(&paths.models, &llm_keys(&config), cassette(&config))?;
 
-    let openai_key =
-        env::var("OPENAI_API_KEY").expect("Environment variable OPENAI_API_KEY not set");
+    info!("Loading Prompt Library from {}", paths.prompts.display());
+    let mut prompts = PromptLibrary::load(&paths.prompts)?;
 
-    println!("Creating Twitter Scrapper");
-    let mut scraper = twitter_scraper::init_scraper().await?;
+    let safety = SafetyPolicy::load(&paths.safety)?;
+    let prices = PriceTable::load(&paths.prices)?;
+    let budget = Budget {
+        daily_usd: config.budget.daily_usd,
+    };
+    if let Some(limit) = budget.daily_usd {
+        info!("Daily budget ${limit:.2}");
+    }
+
+    let schedule = Schedule::load(&paths.schedule)?;
+    let workers = WorkerPools::new(&config.workers);
+    info!("Up to {} runs in flight", workers.max_runs());
+
+    if let Some(addr) = config.telemetry.metrics_addr {
+        pipeline_metrics::install(addr)?;
+    }
+    let shutdown = Shutdown::listen()?;
+
+    info!("Creating Publishers");
+    let publishers = publisher::publishers(accounts(&config, twitter.as_ref()));
 
-    println!("Creating OpenAI Client");
-    let openai_client = providers::openai::Client::new(&openai_key);
+    info!(
+        "Writing runs to {}, history to {}",
+        storage.root().display(),
+        history.path().display()
+    );
+    let ctx = Arc::new(CycleContext {
+        models,
+        publishers,
+        safety,
+        storage,
+        history,
+        prices,
+        workers,
+        config,
+    });
 
-    loop {
-        cycles::tweet_cycle::loops::run_tweet_cycle(&mut scraper, &openai_client).await?;
+    let queue = ClipQueue::load(ctx.config.paths.clip_queue())?;
+    info!(
+        "Rendering up to {} clips ahead, {} waiting in {}",
+        schedule.buffer(),
+        queue.len(),
+        queue.path().display()
+    );
+    let mut scheduler = Scheduler::new(schedule, queue, budget, shutdown);
+    scheduler
+        .run(
+            ctx,
+            twitter.as_deref(),
+            &mut sources,
+            &mut prompts,
+            &mut seen,
+        )
+        .await
+}
+
+fn retention(config: &Config) -> Retention {
+    Retention::new(config.storage.prune_after_days, config.storage.max_total_mb)
+}
 
-        println!("Sleeping before next cycle...");
+fn llm_keys(config: &Config) -> ProviderKeys {
+    ProviderKeys {
+        openai: config.keys.openai.clone(),
+        anthropic: config.keys.anthropic.clone(),
+        ollama_url: Some(config.providers.ollama_url.clone()),
+    }
+}
 
-        tokio::time::sleep(Duration::from_secs(5)).await;
+fn cassette(config: &Config) -> Cassette {
+    let mode = match config.providers.llm_replay {
+        LlmReplay::Off => ReplayMode::Off,
+        LlmReplay::Record => ReplayMode::Record,
+        LlmReplay::Replay => ReplayMode::Replay,
+        LlmReplay::Canned => ReplayMode::Canned,
+    };
+    Cassette::new(mode, &config.paths.llm_fixtures)
+}
+
+fn twitter_credentials(config: &Config) -> Result<TwitterCredentials> {
+    let login = &config.twitter;
+    let (username, password) = login.username_password()?;
+    Ok(TwitterCredentials {
+        username: username.to_string(),
+        password: password.to_string(),
+        email: login.email.clone().filter(|e| !e.is_empty()),
+        two_factor_secret: login.two_factor_secret.clone().filter(|s| !s.is_empty()),
+    })
+}
+
+/// The platforms `config` has credentials for, posting to X through `twitter`.
+fn accounts(config: &Config, twitter: Option<&Arc<Mutex<TwitterSession>>>) -> Accounts {
+    let twitter = match (config.publish.twitter, twitter) {
+        (true, None) => {
+            warn!("Not posting to X without a Twitter session");
+            None
+        }
+        (true, Some(session)) => Some(session.clone()),
+        (false, _) => None,
+    };
+    Accounts {
+        twitter,
+        youtube_token: config.keys.youtube.clone(),
+        tiktok_token: config.keys.tiktok.clone(),
+        instagram: config
+            .keys
+            .instagram
+            .clone()
+            .zip(config.publish.instagram_user_id.clone()),
+    }
+}
+
+/// `juicero gc [--dry-run]`: apply the retention policy to the data root once.
+/// Runs with a clip in the queue are kept.
+fn gc(storage: &Storage, queue: &ClipQueue, dry_run: bool) -> Result<()> {
+    let retention = storage.retention();
+    println!(
+        "Cleaning {}: intermediates older than {} days, cap {}",
+        storage.root().display(),
+        retention.prune_after_days,
+        retention
+            .max_total_bytes
+            .map(|b| format!("{} MB", b / 1024 / 1024))
+            .unwrap_or_else(|| "none".to_string())
+    );
+
+    let report = storage.gc(chrono::Local::now().timestamp(), &queue.run_ids(), dry_run)?;
+    let verb = if dry_run { "Would free" } else { "Freed" };
+    println!(
+        "{verb} {:.1} MB: {} intermediate files pruned, {} of {} runs removed, {:.1} MB left",
+        report.bytes_freed as f64 / 1024.0 / 1024.0,
+        report.files_pruned,
+        report.runs_removed,
+        report.runs_scanned,
+        report.bytes_remaining as f64 / 1024.0 / 1024.0
+    );
+    Ok(())
+}
+
+/// `juicero runs [--limit N] [--status S]`: the latest runs, newest first.
+/// `juicero runs <run_id>`: everything recorded about one run.
+fn runs(history: &RunHistory, args: &[String]) -> Result<()> {
+    let mut limit = 20;
+    let mut status = None;
+    let mut run_id = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--limit needs a number"))?;
+                limit = value
+                    .parse()
+                    .map_err(|_| anyhow!("--limit must be a number, got '{value}'"))?;
+            }
+            "--status" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--status needs a value"))?;
+                status = Some(RunStatus::parse(value)?);
+            }
+            id => {
+                run_id = Some(
+                    id.parse::<i64>()
+                        .map_err(|_| anyhow!("Expected a run id, got '{id}'"))?,
+                )
+            }
+        }
+    }
+
+    let Some(run_id) = run_id else {
+        for run in history.runs(limit, status)? {
+            println!(
+                "{}  {:<9}  ${:<6.3}  {:<24}  {}",
+                run.run_id,
+                run.status,
+                run.cost_usd,
+                run.source.as_deref().unwrap_or("-"),
+                run.topic
+                    .as_deref()
+                    .or(run.status_detail.as_deref())
+                    .unwrap_or("-")
+            );
+        }
+        return Ok(());
+    };
+
+    let detail = history
+        .run(run_id)?
+        .ok_or_else(|| anyhow!("No run {run_id} in {}", history.path().display()))?;
+    let run = &detail.run;
+    println!("Run {} ({})", run.run_id, run.status);
+    if let Some(reason) = &run.status_detail {
+        println!("  reason:   {reason}");
+    }
+    println!("  started:  {}", run.started_at);
+    if let Some(finished) = run.finished_at {
+        println!("  finished: {finished}");
+    }
+    println!(
+        "  source:   {} ({}), section {}",
+        run.source.as_deref().unwrap_or("-"),
+        run.source_key.as_deref().unwrap_or("-"),
+        run.section.as_deref().unwrap_or("-")
+    );
+    println!(
+        "  prompts:  {} as {}",
+        run.prompt_version.as_deref().unwrap_or("-"),
+        run.persona.as_deref().unwrap_or("-")
+    );
+    println!("  topic:    {}", run.topic.as_deref().unwrap_or("-"));
+    println!("  tweet:    {}", run.tweet_text.as_deref().unwrap_or("-"));
+    println!("  dir:      {}", run.run_dir);
+    println!("  video:    {}", run.video_path.as_deref().unwrap_or("-"));
+    println!("  cost:     ${:.4}", run.cost_usd);
+
+    println!("\nStages:");
+    for stage in &detail.stages {
+        println!(
+            "  {:<10} {:>8} ms  {}",
+            stage.stage,
+            stage.duration_ms,
+            stage.error.as_deref().unwrap_or("ok")
+        );
+    }
+    if !detail.costs.is_empty() {
+        println!("\nCosts:");
+        for cost in &detail.costs {
+            println!(
+                "  {:<10} {:<24} {:>10.1} {:<8} ${:.4}",
+                cost.provider, cost.item, cost.quantity, cost.unit, cost.cost_usd
+            );
+        }
+    }
+    println!("\nTweets ({}):", detail.tweets.len());
+    for tweet in &detail.tweets {
+        let marker = if tweet.used_for_topic { "*" } else { " " };
+        println!(
+            "  {marker} {} @{}: {}",
+            tweet.tweet_id,
+            tweet.author,
+            tweet.text.replace('\n', " ")
+        );
+    }
+    if !detail.posts.is_empty() {
+        println!("\nPosts:");
+        for post in &detail.posts {
+            println!(
+                "  {:<10} {} {}",
+                post.platform,
+                post.post_id,
+                post.url.as_deref().unwrap_or("")
+            );
+        }
     }
+    Ok(())
 }
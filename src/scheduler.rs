@@ -0,0 +1,316 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, Utc};
+use cost_meter::Budget;
+use post_scheduler::{Clip, ClipQueue, Schedule, Shutdown};
+use prompt_library::PromptLibrary;
+use publisher::Publisher;
+use source_registry::SourceRegistry;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::Mutex,
+    task::{JoinError, JoinSet},
+};
+use tracing::{error, info, warn};
+use twitter_scraper::{SeenTweetStore, TwitterSession};
+
+use crate::cycles::tweet_cycle::loops::{self, CycleContext};
+
+// Wake up at least this often to re-check the queue, limits and budget.
+const MAX_IDLE: Duration = Duration::from_secs(10 * 60);
+// Pause after a cycle that produced no clip, so a dry source isn't hammered.
+const RETRY_PAUSE: Duration = Duration::from_secs(5);
+// Slots a clip gets when every platform fails it, before it's dropped.
+const MAX_FAILED_SLOTS: u32 = 3;
+
+/// Renders clips ahead into the queue and posts them at the schedule's slots,
+/// within each platform's daily limit, the minimum spacing and the budget.
+pub struct Scheduler {
+    schedule: Schedule,
+    queue: ClipQueue,
+    budget: Budget,
+    shutdown: Shutdown,
+    // The last slot a post was attempted for, posted or not
+    last_slot: Option<DateTime<Utc>>,
+    // The midnight the budget pause was last logged for
+    paused_until: Option<DateTime<Utc>>,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule, queue: ClipQueue, budget: Budget, shutdown: Shutdown) -> Self {
+        Scheduler {
+            schedule,
+            queue,
+            budget,
+            shutdown,
+            last_slot: None,
+            paused_until: None,
+        }
+    }
+
+    /// Loop until a shutdown signal. Up to `workers.runs` runs are in flight
+    /// at once, started one after another and finished concurrently. A failed
+    /// run is recorded and the loop goes on; only the loop's own errors, like
+    /// an unwritable data root, and a panicking run stop it. Either way the
+    /// runs in flight finish first and their clips are queued for the next
+    /// start.
+    pub async fn run(
+        &mut self,
+        ctx: Arc<CycleContext>,
+        twitter: Option<&Mutex<TwitterSession>>,
+        sources: &mut SourceRegistry,
+        prompts: &mut PromptLibrary,
+        seen: &mut SeenTweetStore,
+    ) -> Result<()> {
+        let mut in_flight = JoinSet::new();
+        let mut result = self
+            .schedule_runs(&ctx, &mut in_flight, twitter, sources, prompts, seen)
+            .await;
+        if let Err(e) = &result {
+            error!("Stopping: {e:#}");
+        }
+
+        if !in_flight.is_empty() {
+            info!("Waiting for {} runs in flight to finish", in_flight.len());
+        }
+        while let Some(done) = in_flight.join_next().await {
+            if let Err(e) = self.queue_clip(&ctx, done) {
+                error!("{e:#}");
+                result = result.and(Err(e));
+            }
+        }
+
+        info!(
+            "Shut down with {} clips waiting in {}",
+            self.queue.len(),
+            self.queue.path().display()
+        );
+        result
+    }
+
+    /// Start runs and post clips until a shutdown signal or an error, leaving
+    /// the runs still in flight in `in_flight`.
+    async fn schedule_runs(
+        &mut self,
+        ctx: &Arc<CycleContext>,
+        in_flight: &mut JoinSet<Result<Option<Clip>>>,
+        twitter: Option<&Mutex<TwitterSession>>,
+        sources: &mut SourceRegistry,
+        prompts: &mut PromptLibrary,
+        seen: &mut SeenTweetStore,
+    ) -> Result<()> {
+        let shutdown = self.shutdown.clone();
+        loop {
+            while let Some(done) = in_flight.try_join_next() {
+                self.queue_clip(ctx, done)?;
+            }
+            if shutdown.requested() {
+                return Ok(());
+            }
+
+            self.post_if_due(ctx).await?;
+            pipeline_metrics::clips_queued(self.queue.len());
+            pipeline_metrics::runs_in_flight(in_flight.len());
+
+            let resume = self.budget_resume(ctx)?;
+            let ahead = self.queue.len() + in_flight.len();
+            if ahead < self.schedule.buffer()
+                && in_flight.len() < ctx.workers.max_runs()
+                && resume.is_none()
+            {
+                let mut session = match twitter {
+                    Some(session) => Some(session.lock().await),
+                    None => None,
+                };
+                let started = loops::start_tweet_cycle(
+                    ctx,
+                    session.as_deref_mut(),
+                    sources,
+                    prompts,
+                    seen,
+                    &shutdown,
+                )
+                .await?;
+                drop(session);
+                match started {
+                    Some(run) => {
+                        info!("Run {} is past the safety gate", run.run_id());
+                        in_flight.spawn(loops::finish_tweet_cycle(
+                            ctx.clone(),
+                            run,
+                            shutdown.clone(),
+                        ));
+                    }
+                    None => {
+                        collect_garbage(ctx, &self.queue);
+                        shutdown.sleep(RETRY_PAUSE).await;
+                    }
+                }
+                continue;
+            }
+
+            let wait = self.idle_time(ctx, resume)?;
+            info!(
+                "{} clips waiting, {} runs in flight, checking again in {}s",
+                self.queue.len(),
+                in_flight.len(),
+                wait.as_secs()
+            );
+            let done = tokio::select! {
+                done = in_flight.join_next(), if !in_flight.is_empty() => done,
+                _ = shutdown.sleep(wait) => None,
+            };
+            if let Some(done) = done {
+                self.queue_clip(ctx, done)?;
+            }
+        }
+    }
+
+    /// Queue the clip of a run that finished. A failed run is already in the
+    /// history and skipped here, a run that failed to record how it ended is
+    /// logged. Only a panic in a run is an error.
+    fn queue_clip(
+        &mut self,
+        ctx: &CycleContext,
+        done: Result<Result<Option<Clip>>, JoinError>,
+    ) -> Result<()> {
+        let clip = match done {
+            Ok(Ok(clip)) => clip,
+            Ok(Err(e)) => {
+                error!("A run ended without its outcome recorded: {e:#}");
+                None
+            }
+            Err(e) if e.is_panic() => bail!("A run panicked: {e}"),
+            Err(e) => {
+                warn!("A run was cancelled: {e}");
+                None
+            }
+        };
+        collect_garbage(ctx, &self.queue);
+        if let Some(clip) = clip {
+            info!(
+                "Queued run {}, {} clips waiting",
+                clip.run_id,
+                self.queue.len() + 1
+            );
+            self.queue.push(clip)?;
+        }
+        Ok(())
+    }
+
+    /// Post the oldest clip if a slot is due, the spacing since the last post
+    /// has passed and some platform is under its daily limit. The clip leaves
+    /// the queue once a platform took it, right away without publishers, and
+    /// when every platform failed it in `MAX_FAILED_SLOTS` slots or its video
+    /// can't be read, so a broken clip doesn't hold up the ones behind it.
+    async fn post_if_due(&mut self, ctx: &CycleContext) -> Result<()> {
+        let Some(clip) = self.queue.front().cloned() else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let last_post = ctx.history.last_post_at()?;
+        let Some(slot) = self.schedule.due_slot(now, last_post.max(self.last_slot)) else {
+            return Ok(());
+        };
+        if self.schedule.spacing_left(now, last_post).is_some() {
+            return Ok(());
+        }
+        self.last_slot = Some(now);
+
+        let start_of_day = self.schedule.start_of_day(now);
+        let mut open: Vec<&dyn Publisher> = Vec::new();
+        for publisher in &ctx.publishers {
+            let platform = publisher.platform();
+            if self
+                .schedule
+                .allows(platform, ctx.history.posts_since(platform, start_of_day)?)
+            {
+                open.push(publisher.as_ref());
+            } else {
+                info!("{platform} has all its posts for today, skipping it");
+            }
+        }
+        if open.is_empty() && !ctx.publishers.is_empty() {
+            info!(
+                "Every platform is at its daily limit, keeping run {} for later",
+                clip.run_id
+            );
+            return Ok(());
+        }
+
+        info!(
+            "Posting run {} for the {} slot",
+            clip.run_id,
+            slot.with_timezone(&self.schedule.timezone())
+                .format("%a %H:%M %Z")
+        );
+        let published = loops::publish_clip(ctx, &clip, &open).await?;
+        if published.posts.is_empty() && !open.is_empty() {
+            if let Some(reason) = published.unreadable_video {
+                warn!("Dropping run {}: {reason}", clip.run_id);
+            } else {
+                let failed = self.queue.record_failure()?;
+                if failed < MAX_FAILED_SLOTS {
+                    warn!(
+                        "Every platform failed, keeping run {} for the next slot ({failed} of {MAX_FAILED_SLOTS} tries)",
+                        clip.run_id
+                    );
+                    return Ok(());
+                }
+                warn!(
+                    "Every platform failed run {} in {failed} slots, dropping it",
+                    clip.run_id
+                );
+            }
+        }
+        self.queue.pop()?;
+        Ok(())
+    }
+
+    /// When rendering may go on again if today's spending is over the budget.
+    /// Spending is counted per day in the schedule's timezone.
+    fn budget_resume(&mut self, ctx: &CycleContext) -> Result<Option<DateTime<Utc>>> {
+        let now = Utc::now();
+        let spent = ctx.history.spent_since(self.schedule.start_of_day(now))?;
+        pipeline_metrics::spent_today(spent);
+        if !self.budget.exceeded(spent) {
+            return Ok(None);
+        }
+        let resume = self.schedule.start_of_next_day(now);
+        if self.paused_until != Some(resume) {
+            info!(
+                "Spent ${spent:.2} of the ${:.2} daily budget, not rendering until {resume}",
+                self.budget.daily_usd.unwrap_or_default()
+            );
+            self.paused_until = Some(resume);
+        }
+        Ok(Some(resume))
+    }
+
+    /// How long to sleep with nothing to render: until the next slot, the end
+    /// of the spacing or the budget pause, whichever comes first.
+    fn idle_time(&self, ctx: &CycleContext, resume: Option<DateTime<Utc>>) -> Result<Duration> {
+        let now = Utc::now();
+        let until = |at: DateTime<Utc>| (at - now).to_std().unwrap_or_default();
+        let spacing = self.schedule.spacing_left(now, ctx.history.last_post_at()?);
+        let wait = [
+            self.schedule.next_slot(now).map(until),
+            spacing,
+            resume.map(until),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(MAX_IDLE);
+        Ok(wait.clamp(Duration::from_secs(1), MAX_IDLE))
+    }
+}
+
+/// Retention runs after every cycle, a failed pass shouldn't stop the bot.
+fn collect_garbage(ctx: &CycleContext, queue: &ClipQueue) {
+    if let Err(e) = ctx
+        .storage
+        .gc(Local::now().timestamp(), &queue.run_ids(), false)
+    {
+        warn!("Cleaning up old runs failed: {e}");
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::{anyhow, Result};
+use bot_config::LogFormat;
+use tracing_subscriber::{
+    filter::filter_fn, fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+use crate::logger::{BannerLayer, AGENT_TARGET};
+
+/// Set up tracing in `format`: `pretty` is the coloured console with the agent
+/// banners, `json` is one JSON object per line with the `run`, `stage` and
+/// provider spans of every event. `RUST_LOG` is the usual filter, default `info`.
+pub fn init(format: LogFormat) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Pretty => registry
+            .with(
+                fmt::layer()
+                    .with_target(false)
+                    .with_filter(filter_fn(|meta| meta.target() != AGENT_TARGET)),
+            )
+            .with(BannerLayer)
+            .try_init(),
+        LogFormat::Json => registry
+            .with(
+                fmt::layer()
+                    .json()
+                    .with_current_span(false)
+                    .with_span_list(true),
+            )
+            .try_init(),
+    }
+    .map_err(|e| anyhow!("Failed to set up logging: {e}"))
+}
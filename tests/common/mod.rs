@@ -0,0 +1,18 @@
+use std::{env, process::Command};
+
+/// The ffmpeg or ffprobe binary the bot would use, `FFMPEG_PATH` style override first.
+pub fn tool(var: &str, default: &str) -> String {
+    env::var(var).unwrap_or_else(|_| default.to_string())
+}
+
+/// Whether ffmpeg and ffprobe run, the tests that render fail without them.
+pub fn ffmpeg_available() -> bool {
+    [tool("FFMPEG_PATH", "ffmpeg"), tool("FFPROBE_PATH", "ffprobe")]
+        .iter()
+        .all(|tool| {
+            Command::new(tool)
+                .arg("-version")
+                .output()
+                .is_ok_and(|out| out.status.success())
+        })
+}
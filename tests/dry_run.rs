@@ -0,0 +1,57 @@
+//! `juicero dry-run` end to end: it has to leave a video ffprobe can read.
+//!
+//! Needs ffmpeg and ffprobe (or `FFMPEG_PATH` and `FFPROBE_PATH`), so it only
+//! runs with `cargo test -- --ignored` and fails if they are missing.
+
+use std::{env, path::Path, process::Command};
+
+mod common;
+
+#[test]
+#[ignore = "needs ffmpeg"]
+fn dry_run_renders_a_video_ffprobe_reads() {
+    assert!(
+        common::ffmpeg_available(),
+        "ffmpeg or ffprobe not found, set FFMPEG_PATH and FFPROBE_PATH"
+    );
+    let data_root = env::temp_dir().join(format!("dry-run-{}", std::process::id()));
+
+    let out = Command::new(env!("CARGO_BIN_EXE_juicero"))
+        .arg("dry-run")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("DATA_ROOT", &data_root)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stdout}\n{stderr}");
+
+    // "Dry run <id> rendered <path> (<seconds>s): <caption>"
+    let line = stdout
+        .lines()
+        .find(|l| l.starts_with("Dry run "))
+        .unwrap_or_else(|| panic!("no result line: {stdout}"));
+    let (path, rest) = line
+        .split_once(" rendered ")
+        .and_then(|(_, rest)| rest.split_once(" ("))
+        .unwrap();
+    let (seconds, caption) = rest.split_once("s): ").unwrap();
+    let seconds: f64 = seconds.parse().unwrap();
+
+    assert!(Path::new(path).starts_with(data_root.join("dry_run")), "{path}");
+    assert!(Path::new(path).is_file(), "{path} is missing");
+    assert!(seconds > 0.0, "{line}");
+    assert!(!caption.trim().is_empty(), "{line}");
+
+    // ffprobe agrees on the length the dry run reported
+    let probe = Command::new(common::tool("FFPROBE_PATH", "ffprobe"))
+        .args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1", path])
+        .output()
+        .unwrap();
+    assert!(probe.status.success(), "ffprobe can't read {path}");
+    let probed: f64 = String::from_utf8_lossy(&probe.stdout).trim().parse().unwrap();
+    assert!((probed - seconds).abs() < 0.1, "ffprobe says {probed}s, dry run said {seconds}s");
+
+    let _ = std::fs::remove_dir_all(&data_root);
+}
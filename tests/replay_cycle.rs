@@ -0,0 +1,41 @@
+//! A whole cycle, from the saved tweets to a rendered clip, with every LLM
+//! answer replayed from the canned cassette in `fixtures/canned_cassette`.
+//!
+//! Needs ffmpeg and ffprobe (or `FFMPEG_PATH` and `FFPROBE_PATH`), so it only
+//! runs with `cargo test -- --ignored` and fails if they are missing.
+
+use std::{env, path::PathBuf, process::Command};
+
+mod common;
+
+#[test]
+#[ignore = "needs ffmpeg"]
+fn a_cycle_replays_from_the_canned_cassette() {
+    assert!(
+        common::ffmpeg_available(),
+        "ffmpeg or ffprobe not found, set FFMPEG_PATH and FFPROBE_PATH"
+    );
+    let data_root = env::temp_dir().join(format!("replay-cycle-{}", std::process::id()));
+    let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    let out = Command::new(env!("CARGO_BIN_EXE_juicero"))
+        .arg("dry-run")
+        .current_dir(&repo)
+        .env("DATA_ROOT", &data_root)
+        .env("LLM_REPLAY", "replay")
+        .env("LLM_FIXTURES_DIR", repo.join("fixtures/canned_cassette/llm"))
+        .env("MODELS_PATH", repo.join("fixtures/canned_cassette/models.toml"))
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&data_root);
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stdout}\n{stderr}");
+    // A prompt that changed since the cassette was made misses it and fails the run
+    assert!(!stderr.contains("No LLM fixture"), "{stderr}");
+    assert!(
+        stdout.contains("my fridge asked how my day was"),
+        "the saved tweet wasn't posted: {stdout}"
+    );
+}